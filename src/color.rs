@@ -0,0 +1,73 @@
+//! ANSI colors for the schedule views, turned on and off with `--color`.
+
+use std::{
+    env, fmt,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use clap::ArgEnum;
+
+/// When to color the output.
+#[derive(ArgEnum, Debug, Clone, Copy)]
+pub enum ColorChoice {
+    /// when stdout is a terminal and `NO_COLOR` isn't set.
+    Auto,
+    Always,
+    Never,
+}
+
+/// What a piece of output is, which decides its color.
+#[derive(Debug, Clone, Copy)]
+pub enum Style {
+    /// day headings.
+    Heading,
+    Event,
+    Time,
+    Command,
+    /// events that are already over or disabled.
+    Dim,
+}
+
+impl Style {
+    fn code(self) -> &'static str {
+        match self {
+            Style::Heading => "1",
+            Style::Event => "1;36",
+            Style::Time => "33",
+            Style::Command => "32",
+            Style::Dim => "2",
+        }
+    }
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Decide once whether `paint` colors anything.
+pub fn init(choice: ColorChoice) {
+    let enabled = match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            // https://no-color.org asks for any non-empty value to count.
+            let no_color = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+            // SAFETY: isatty only looks at the file descriptor.
+            !no_color && unsafe { libc::isatty(libc::STDOUT_FILENO) } == 1
+        }
+    };
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// `text` in the color for `style`, or as it is with colors off. A trailing newline is kept
+/// outside the color so the next line starts clean.
+pub fn paint(style: Style, text: impl fmt::Display) -> String {
+    let text = text.to_string();
+    if !ENABLED.load(Ordering::Relaxed) {
+        return text;
+    }
+
+    let (body, newline) = match text.strip_suffix('\n') {
+        Some(body) => (body, "\n"),
+        None => (text.as_str(), ""),
+    };
+    format!("\x1b[{}m{}\x1b[0m{}", style.code(), body, newline)
+}
@@ -0,0 +1,456 @@
+//! Shell completion scripts, generated from the clap definition of the CLI.
+
+use std::fmt::Write;
+
+use clap::{App, AppSettings, Arg, ArgEnum, ArgSettings, ValueHint};
+
+/// Shells we can generate completions for.
+#[derive(ArgEnum, Debug, Clone, Copy)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+}
+
+/// Names from the config that completion scripts ask for through `eventjoiner names`.
+#[derive(ArgEnum, Debug, Clone, Copy)]
+pub enum NameKind {
+    Commands,
+    Events,
+    Profiles,
+}
+
+/// Arguments whose values are names from the config, and the `names` kinds to complete them.
+const DYNAMIC: &[(&str, &str)] = &[
+    ("launch", "commands"),
+    ("event", "events"),
+    ("show-command", "commands events"),
+    ("show-event", "events"),
+    ("profile", "profiles"),
+];
+
+/// What to complete as the value of an argument.
+enum Value<'a> {
+    /// anything, nothing to suggest.
+    Any,
+    Files,
+    Choices(Vec<&'a str>),
+    /// names from the config, the argument to `eventjoiner names`.
+    Names(&'static str),
+}
+
+struct Opt<'a> {
+    short: Option<char>,
+    long: Option<&'a str>,
+    help: String,
+    value: Option<Value<'a>>,
+}
+
+impl Opt<'_> {
+    /// the spellings of this option, e.g. `-l` and `--launch`.
+    fn words(&self) -> Vec<String> {
+        let mut words = Vec::new();
+        if let Some(short) = self.short {
+            words.push(format!("-{}", short));
+        }
+        if let Some(long) = self.long {
+            words.push(format!("--{}", long));
+        }
+        words
+    }
+}
+
+/// The options, positional value and subcommands of a command.
+struct Level<'a> {
+    name: &'a str,
+    about: String,
+    opts: Vec<Opt<'a>>,
+    positional: Option<Value<'a>>,
+    subcommands: Vec<Level<'a>>,
+}
+
+impl<'a> Level<'a> {
+    fn new(app: &'a App) -> Self {
+        let mut opts: Vec<Opt> = app
+            .get_arguments()
+            // clap's own --help and --version placeholders only get their final shape, or are
+            // dropped, once the app is built.
+            .filter(|arg| !matches!(arg.get_name(), "help" | "version"))
+            .filter(|arg| !arg.is_positional() && !arg.is_set(ArgSettings::Hidden))
+            .map(|arg| Opt {
+                short: arg.get_short(),
+                long: arg.get_long(),
+                help: one_line(arg.get_help()),
+                value: arg.is_set(ArgSettings::TakesValue).then(|| value(arg)),
+            })
+            .collect();
+        opts.push(Opt {
+            short: Some('h'),
+            long: Some("help"),
+            help: "print help information".to_string(),
+            value: None,
+        });
+
+        Level {
+            name: app.get_name(),
+            about: one_line(app.get_about()),
+            opts,
+            positional: app.get_positionals().next().map(value),
+            subcommands: app
+                .get_subcommands()
+                .filter(|sub| !sub.is_set(AppSettings::Hidden))
+                .map(Level::new)
+                .collect(),
+        }
+    }
+
+    /// every word that may come next when not completing an option's value.
+    fn words(&self) -> Vec<String> {
+        let mut words: Vec<String> = self.opts.iter().flat_map(Opt::words).collect();
+        words.extend(self.subcommands.iter().map(|sub| sub.name.to_string()));
+        if let Some(Value::Choices(choices)) = &self.positional {
+            words.extend(choices.iter().map(|choice| choice.to_string()));
+        }
+        words
+    }
+}
+
+fn value<'a>(arg: &'a Arg) -> Value<'a> {
+    if let Some((_, kinds)) = DYNAMIC.iter().find(|(name, _)| *name == arg.get_name()) {
+        return Value::Names(kinds);
+    }
+    if let Some(values) = arg.get_possible_values() {
+        return Value::Choices(
+            values
+                .iter()
+                .filter(|value| !value.is_hidden())
+                .map(|value| value.get_name())
+                .collect(),
+        );
+    }
+    match arg.get_value_hint() {
+        ValueHint::AnyPath | ValueHint::FilePath | ValueHint::DirPath => Value::Files,
+        _ => Value::Any,
+    }
+}
+
+fn one_line(help: Option<&str>) -> String {
+    help.unwrap_or_default()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The completion script for `shell`, completing the commands and options of `app`.
+pub fn generate(shell: Shell, app: &App) -> String {
+    let top = Level::new(app);
+    match shell {
+        Shell::Bash => bash(&top),
+        Shell::Zsh => zsh(&top),
+        Shell::Fish => fish(&top),
+        Shell::Powershell => powershell(&top),
+    }
+}
+
+fn bash(top: &Level) -> String {
+    let bin = top.name;
+    let subs: Vec<&str> = top.subcommands.iter().map(|sub| sub.name).collect();
+
+    let mut out = String::new();
+    let _ = writeln!(out, "_{}() {{", bin);
+    out.push_str("    local cur prev sub i\n");
+    out.push_str("    cur=\"${COMP_WORDS[COMP_CWORD]}\"\n");
+    out.push_str("    prev=\"${COMP_WORDS[COMP_CWORD-1]}\"\n");
+    out.push_str("    sub=\"\"\n");
+    out.push_str("    for ((i = 1; i < COMP_CWORD; i++)); do\n");
+    out.push_str("        case \"${COMP_WORDS[i]}\" in\n");
+    let _ = writeln!(
+        out,
+        "            {}) sub=\"${{COMP_WORDS[i]}}\"; break ;;",
+        subs.join("|")
+    );
+    out.push_str("        esac\n");
+    out.push_str("    done\n\n");
+    out.push_str("    case \"$sub\" in\n");
+
+    let levels =
+        std::iter::once(("\"\"", top)).chain(top.subcommands.iter().map(|sub| (sub.name, sub)));
+    for (pattern, level) in levels {
+        let _ = writeln!(out, "        {})", pattern);
+        let mut cases = String::new();
+        for opt in &level.opts {
+            let reply = match &opt.value {
+                None => continue,
+                Some(Value::Any) => "return".to_string(),
+                Some(Value::Files) => "COMPREPLY=($(compgen -f -- \"$cur\")); return".to_string(),
+                Some(Value::Choices(choices)) => format!(
+                    "COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")); return",
+                    choices.join(" ")
+                ),
+                Some(Value::Names(kinds)) => format!(
+                    "COMPREPLY=($(compgen -W \"$({} names {} 2>/dev/null)\" -- \"$cur\")); return",
+                    bin, kinds
+                ),
+            };
+            let _ = writeln!(
+                cases,
+                "                {}) {} ;;",
+                opt.words().join("|"),
+                reply
+            );
+        }
+        if !cases.is_empty() {
+            out.push_str("            case \"$prev\" in\n");
+            out.push_str(&cases);
+            out.push_str("            esac\n");
+        }
+        let _ = writeln!(
+            out,
+            "            COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))",
+            level.words().join(" ")
+        );
+        out.push_str("            ;;\n");
+    }
+
+    out.push_str("    esac\n");
+    out.push_str("}\n\n");
+    let _ = writeln!(out, "complete -F _{} {}", bin, bin);
+    out
+}
+
+/// escape `text` for a zsh single quoted `[description]`.
+fn zsh_escape(text: &str) -> String {
+    text.replace('\'', "'\\''")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+}
+
+fn zsh_action(bin: &str, value: &Value) -> String {
+    match value {
+        Value::Any => " ".to_string(),
+        Value::Files => "_files".to_string(),
+        Value::Choices(choices) => format!("({})", choices.join(" ")),
+        Value::Names(kinds) => format!("{{compadd -- $({} names {} 2>/dev/null)}}", bin, kinds),
+    }
+}
+
+fn zsh_specs(bin: &str, level: &Level) -> Vec<String> {
+    let mut specs = Vec::new();
+    for opt in &level.opts {
+        let help = zsh_escape(&opt.help);
+        for (word, sep) in opt
+            .short
+            .map(|short| (format!("-{}", short), "+"))
+            .into_iter()
+            .chain(opt.long.map(|long| (format!("--{}", long), "=")))
+        {
+            specs.push(match &opt.value {
+                None => format!("'{}[{}]'", word, help),
+                Some(value) => format!(
+                    "'{}{}[{}]:value:{}'",
+                    word,
+                    sep,
+                    help,
+                    zsh_action(bin, value)
+                ),
+            });
+        }
+    }
+    if let Some(value) = &level.positional {
+        specs.push(format!("'1:value:{}'", zsh_action(bin, value)));
+    }
+    specs
+}
+
+fn zsh(top: &Level) -> String {
+    let bin = top.name;
+
+    let mut out = String::new();
+    let _ = writeln!(out, "#compdef {}\n", bin);
+    let _ = writeln!(out, "_{}() {{", bin);
+    out.push_str("    local line state\n\n");
+    out.push_str("    _arguments -C \\\n");
+    for spec in zsh_specs(bin, top) {
+        let _ = writeln!(out, "        {} \\", spec);
+    }
+    out.push_str("        '1: :->subcommands' \\\n");
+    out.push_str("        '*:: :->args'\n\n");
+    out.push_str("    case $state in\n");
+    out.push_str("        subcommands)\n");
+    out.push_str("            _values 'subcommand' \\\n");
+    let subs: Vec<String> = top
+        .subcommands
+        .iter()
+        .map(|sub| format!("'{}[{}]'", sub.name, zsh_escape(&sub.about)))
+        .collect();
+    let _ = writeln!(
+        out,
+        "                {}",
+        subs.join(" \\\n                ")
+    );
+    out.push_str("            ;;\n");
+    out.push_str("        args)\n");
+    out.push_str("            case $line[1] in\n");
+    for sub in &top.subcommands {
+        let _ = writeln!(
+            out,
+            "                {}) _arguments {} ;;",
+            sub.name,
+            zsh_specs(bin, sub).join(" ")
+        );
+    }
+    out.push_str("            esac\n");
+    out.push_str("            ;;\n");
+    out.push_str("    esac\n");
+    out.push_str("}\n\n");
+    let _ = writeln!(
+        out,
+        "if [ \"$funcstack[1]\" = \"_{bin}\" ]; then\n    _{bin} \"$@\"\nelse\n    compdef _{bin} {bin}\nfi",
+        bin = bin
+    );
+    out
+}
+
+/// quote `text` for fish.
+fn fish_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+fn fish_value(bin: &str, value: &Value) -> String {
+    match value {
+        Value::Any => "-r -f".to_string(),
+        Value::Files => "-r -F".to_string(),
+        Value::Choices(choices) => format!("-r -f -a \"{}\"", choices.join(" ")),
+        Value::Names(kinds) => format!("-r -f -a \"({} names {} 2>/dev/null)\"", bin, kinds),
+    }
+}
+
+fn fish(top: &Level) -> String {
+    let bin = top.name;
+
+    let mut out = String::new();
+    let _ = writeln!(out, "complete -c {} -f", bin);
+
+    let levels = std::iter::once(("__fish_use_subcommand".to_string(), top)).chain(
+        top.subcommands
+            .iter()
+            .map(|sub| (format!("__fish_seen_subcommand_from {}", sub.name), sub)),
+    );
+    for (condition, level) in levels {
+        for opt in &level.opts {
+            let mut line = format!("complete -c {} -n \"{}\"", bin, condition);
+            if let Some(short) = opt.short {
+                let _ = write!(line, " -s {}", short);
+            }
+            if let Some(long) = opt.long {
+                let _ = write!(line, " -l {}", long);
+            }
+            let _ = write!(line, " -d {}", fish_quote(&opt.help));
+            if let Some(value) = &opt.value {
+                let _ = write!(line, " {}", fish_value(bin, value));
+            }
+            let _ = writeln!(out, "{}", line);
+        }
+        if let Some(value) = &level.positional {
+            let _ = writeln!(
+                out,
+                "complete -c {} -n \"{}\" {}",
+                bin,
+                condition,
+                fish_value(bin, value).trim_start_matches("-r ")
+            );
+        }
+    }
+
+    for sub in &top.subcommands {
+        let _ = writeln!(
+            out,
+            "complete -c {} -n \"__fish_use_subcommand\" -f -a {} -d {}",
+            bin,
+            sub.name,
+            fish_quote(&sub.about)
+        );
+    }
+    out
+}
+
+/// quote `text` for powershell.
+fn ps_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
+
+fn ps_list<S: AsRef<str>>(words: &[S]) -> String {
+    let words: Vec<String> = words.iter().map(|word| ps_quote(word.as_ref())).collect();
+    format!("@({})", words.join(", "))
+}
+
+fn powershell(top: &Level) -> String {
+    let bin = top.name;
+    let subs: Vec<&str> = top.subcommands.iter().map(|sub| sub.name).collect();
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "Register-ArgumentCompleter -Native -CommandName {} -ScriptBlock {{",
+        ps_quote(bin)
+    );
+    out.push_str("    param($wordToComplete, $commandAst, $cursorPosition)\n\n");
+    out.push_str("    $words = @($commandAst.CommandElements |\n");
+    out.push_str("        Where-Object { $_.Extent.EndOffset -lt $cursorPosition } |\n");
+    out.push_str("        ForEach-Object { $_.ToString() })\n");
+    out.push_str("    $prev = $words[-1]\n");
+    out.push_str("    $sub = ''\n");
+    let _ = writeln!(
+        out,
+        "    foreach ($word in $words) {{ if ({} -contains $word) {{ $sub = $word; break }} }}\n",
+        ps_list(&subs)
+    );
+    out.push_str("    $candidates = @()\n");
+
+    let levels =
+        std::iter::once(("", top)).chain(top.subcommands.iter().map(|sub| (sub.name, sub)));
+    for (idx, (name, level)) in levels.enumerate() {
+        let _ = writeln!(
+            out,
+            "    {} ($sub -eq {}) {{",
+            if idx == 0 { "if" } else { "elseif" },
+            ps_quote(name)
+        );
+        let mut first = true;
+        for opt in &level.opts {
+            let body = match &opt.value {
+                None => continue,
+                // let powershell fall back to its own completion.
+                Some(Value::Any) | Some(Value::Files) => "return".to_string(),
+                Some(Value::Choices(choices)) => format!("$candidates = {}", ps_list(choices)),
+                Some(Value::Names(kinds)) => {
+                    format!("$candidates = @(& {} names {} 2>$null)", bin, kinds)
+                }
+            };
+            let _ = writeln!(
+                out,
+                "        {} ({} -contains $prev) {{ {} }}",
+                if first { "if" } else { "elseif" },
+                ps_list(&opt.words()),
+                body
+            );
+            first = false;
+        }
+        let words = ps_list(&level.words());
+        if first {
+            let _ = writeln!(out, "        $candidates = {}", words);
+        } else {
+            let _ = writeln!(out, "        else {{ $candidates = {} }}", words);
+        }
+        out.push_str("    }\n");
+    }
+
+    out.push_str(
+        "\n    $candidates | Where-Object { $_ -like \"$wordToComplete*\" } | ForEach-Object {\n",
+    );
+    out.push_str("        [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)\n");
+    out.push_str("    }\n");
+    out.push_str("}\n");
+    out
+}
@@ -0,0 +1,143 @@
+//! `--format` templates, which print each event of `--list`, `--list-all` and `--next` as a
+//! single line of the user's choosing.
+
+use std::{str::FromStr, time::Duration as StdDuration};
+
+use chrono::{NaiveDate, NaiveTime};
+use eventjoiner::Day;
+
+use crate::format_countdown;
+
+/// The placeholders a template can use, and the help text listing them.
+pub const FIELDS: &str = "{event}, {time}, {end_time}, {date}, {weekday}, {command} and \
+{countdown}";
+
+/// A value of an event's that can be filled into a template.
+#[derive(Debug, Clone, Copy)]
+enum Field {
+    Event,
+    /// its start as HH:MM.
+    Time,
+    /// its end_time as HH:MM, empty without one.
+    EndTime,
+    /// its date as YYYY-MM-DD, empty for `--list-all`.
+    Date,
+    Weekday,
+    /// the names of the commands it launches, separated by commas, or the binary of its
+    /// own `command`.
+    Command,
+    /// how long until it starts like "1h 5m", empty once it has.
+    Countdown,
+}
+
+impl FromStr for Field {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Ok(match name {
+            "event" => Field::Event,
+            "time" => Field::Time,
+            "end_time" => Field::EndTime,
+            "date" => Field::Date,
+            "weekday" => Field::Weekday,
+            "command" => Field::Command,
+            "countdown" => Field::Countdown,
+            _ => {
+                return Err(format!(
+                    "unknown placeholder {{{}}}, expected one of {}",
+                    name, FIELDS
+                ))
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Piece {
+    Text(String),
+    Field(Field),
+}
+
+/// A parsed `--format` string. `{{` and `}}` stand for plain braces.
+#[derive(Debug, Clone)]
+pub struct Template(Vec<Piece>);
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(template: &str) -> Result<Self, Self::Err> {
+        let mut pieces = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let (name, rest) = chars
+                        .as_str()
+                        .split_once('}')
+                        .ok_or_else(|| format!("unclosed {{ in {}", template))?;
+                    let field = name.parse()?;
+                    chars = rest.chars();
+                    if !text.is_empty() {
+                        pieces.push(Piece::Text(std::mem::take(&mut text)));
+                    }
+                    pieces.push(Piece::Field(field));
+                }
+                '}' => return Err(format!("unmatched }} in {}, write }}}} for one", template)),
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            pieces.push(Piece::Text(text));
+        }
+
+        Ok(Template(pieces))
+    }
+}
+
+/// What a template is filled in with for one event.
+pub struct Row<'a> {
+    pub event: &'a str,
+    pub time: NaiveTime,
+    pub end_time: Option<NaiveTime>,
+    pub date: Option<NaiveDate>,
+    pub day: Day,
+    pub commands: &'a [String],
+    /// `None` once the event has started, or when it isn't about a particular date.
+    pub countdown: Option<StdDuration>,
+}
+
+impl Template {
+    pub fn render(&self, row: &Row) -> String {
+        let mut out = String::new();
+        for piece in &self.0 {
+            match piece {
+                Piece::Text(text) => out.push_str(text),
+                Piece::Field(field) => out.push_str(&match field {
+                    Field::Event => row.event.to_string(),
+                    Field::Time => row.time.format("%H:%M").to_string(),
+                    Field::EndTime => row
+                        .end_time
+                        .map(|end| end.format("%H:%M").to_string())
+                        .unwrap_or_default(),
+                    Field::Date => row
+                        .date
+                        .map(|date| date.format("%Y-%m-%d").to_string())
+                        .unwrap_or_default(),
+                    Field::Weekday => row.day.to_string(),
+                    Field::Command => row.commands.join(", "),
+                    Field::Countdown => row.countdown.map(format_countdown).unwrap_or_default(),
+                }),
+            }
+        }
+        out
+    }
+}
@@ -0,0 +1,840 @@
+//! Just enough of iCalendar (RFC 5545) to turn an exported schedule into a timetable for
+//! `import-ical`, and the timetable into a calendar for `export-ical`.
+//!
+//! On import weekly recurring events become timetable entries and everything else with a
+//! start time becomes a one-off event. Anything the config can't express is skipped with a
+//! warning.
+//!
+//! This isn't built on the `icalendar` crate: we only need a handful of `VEVENT` properties,
+//! which is less code than adapting its types to ours and keeps the dependencies down to
+//! what the daemon already needs.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+};
+
+use chrono::{
+    Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, Offset, TimeZone, Utc, Weekday,
+};
+use eventjoiner::{Config, Day, Event};
+use log::warn;
+use serde::Serialize;
+
+/// The config generated from a calendar, the parts `import-ical` prints.
+#[derive(Debug, Default, Serialize)]
+pub struct Import {
+    notify_before: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timezone: Option<String>,
+    /// left empty for the user to fill in.
+    command: BTreeMap<String, String>,
+    events: BTreeMap<String, Vec<String>>,
+    timetable: BTreeMap<String, Vec<Entry>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    once: Vec<OnceEntry>,
+}
+
+#[derive(Debug, Serialize)]
+struct Entry {
+    time: NaiveTime,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end_time: Option<NaiveTime>,
+    event: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    interval_weeks: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    anchor_date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Serialize)]
+struct OnceEntry {
+    date: NaiveDate,
+    time: NaiveTime,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end_time: Option<NaiveTime>,
+    event: String,
+}
+
+/// A property line, `NAME;PARAM=VALUE:value`.
+struct Property<'a> {
+    name: String,
+    params: Vec<(String, &'a str)>,
+    value: &'a str,
+}
+
+impl<'a> Property<'a> {
+    fn parse(line: &'a str) -> Option<Self> {
+        let (head, value) = line.split_once(':')?;
+        let mut parts = head.split(';');
+        let name = parts.next()?.to_ascii_uppercase();
+        let params = parts
+            .filter_map(|param| param.split_once('='))
+            .map(|(key, value)| (key.to_ascii_uppercase(), value.trim_matches('"')))
+            .collect();
+        Some(Property {
+            name,
+            params,
+            value,
+        })
+    }
+
+    fn param(&self, name: &str) -> Option<&'a str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| *value)
+    }
+}
+
+/// A `DTSTART` or `DTEND`, as wall time in whichever zone it was given.
+struct Moment {
+    at: NaiveDateTime,
+    tzid: Option<String>,
+}
+
+impl Moment {
+    /// Parse a date-time value. All-day dates aren't supported, UTC times are moved to the
+    /// local time zone.
+    fn parse(property: &Property) -> Result<Self, String> {
+        if property.param("VALUE") == Some("DATE") || !property.value.contains('T') {
+            return Err("all-day events aren't supported".to_string());
+        }
+
+        let (value, utc) = match property.value.strip_suffix('Z') {
+            Some(value) => (value, true),
+            None => (property.value, false),
+        };
+        let at = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+            .map_err(|_| format!("invalid date-time {}", property.value))?;
+
+        Ok(if utc {
+            Moment {
+                at: Utc
+                    .from_utc_datetime(&at)
+                    .with_timezone(&Local)
+                    .naive_local(),
+                tzid: None,
+            }
+        } else {
+            Moment {
+                at,
+                tzid: property.param("TZID").map(str::to_string),
+            }
+        })
+    }
+}
+
+/// One `VEVENT`, with the properties we care about.
+#[derive(Default)]
+struct VEvent<'a> {
+    summary: Option<String>,
+    start: Option<Property<'a>>,
+    end: Option<Property<'a>>,
+    rrule: Option<&'a str>,
+    exdate: bool,
+}
+
+/// Undo line folding, where a line starting with a space or tab continues the previous one.
+fn unfold(contents: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in contents.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+/// Undo the escaping of a text value.
+fn unescape(value: &str) -> String {
+    let mut out = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            // names are single line, so newlines become spaces.
+            Some('n') | Some('N') => out.push(' '),
+            Some(c) => out.push(c),
+            None => {}
+        }
+    }
+    out.trim().to_string()
+}
+
+fn weekday(code: &str) -> Option<Weekday> {
+    Some(match code {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return None,
+    })
+}
+
+/// The days and week interval of a weekly `RRULE`, refusing anything fancier.
+fn weekly(rrule: &str, start: Weekday, summary: &str) -> Result<(Vec<Weekday>, u32), String> {
+    let mut days = vec![start];
+    let mut interval = 1;
+    let mut weekly = false;
+
+    for part in rrule.split(';') {
+        let (key, value) = part
+            .split_once('=')
+            .ok_or_else(|| format!("invalid RRULE part {}", part))?;
+        match key.to_ascii_uppercase().as_str() {
+            "FREQ" if value.eq_ignore_ascii_case("WEEKLY") => weekly = true,
+            "FREQ" => {
+                return Err(format!(
+                    "only weekly recurrence is supported, not {}",
+                    value
+                ))
+            }
+            "INTERVAL" => {
+                interval = value
+                    .parse()
+                    .ok()
+                    .filter(|&n| n > 0)
+                    .ok_or_else(|| format!("invalid INTERVAL {}", value))?
+            }
+            "BYDAY" => {
+                days = value
+                    .split(',')
+                    .map(|code| weekday(code).ok_or_else(|| format!("unsupported BYDAY {}", code)))
+                    .collect::<Result<_, _>>()?
+            }
+            "UNTIL" | "COUNT" => warn!(
+                "{}: ignoring the end of its recurrence ({}), use valid_until instead",
+                summary, part
+            ),
+            "WKST" => {}
+            _ => return Err(format!("unsupported RRULE part {}", part)),
+        }
+    }
+
+    if !weekly {
+        return Err("RRULE has no FREQ".to_string());
+    }
+    Ok((days, interval))
+}
+
+impl Import {
+    /// Convert the events of an iCalendar file, warning about the ones that are skipped.
+    pub fn from_ical(contents: &str) -> Result<Self, String> {
+        let lines = unfold(contents);
+        if !lines
+            .iter()
+            .any(|line| line.trim().eq_ignore_ascii_case("BEGIN:VCALENDAR"))
+        {
+            return Err("not an iCalendar file, there's no BEGIN:VCALENDAR".to_string());
+        }
+
+        let mut import = Import {
+            notify_before: 5,
+            ..Import::default()
+        };
+        let mut tzids = BTreeSet::new();
+        let mut current: Option<VEvent> = None;
+        for line in &lines {
+            let property = match Property::parse(line.trim_end()) {
+                Some(property) => property,
+                None => continue,
+            };
+
+            match (property.name.as_str(), &mut current) {
+                ("BEGIN", None) if property.value.eq_ignore_ascii_case("VEVENT") => {
+                    current = Some(VEvent::default())
+                }
+                ("END", Some(_)) if property.value.eq_ignore_ascii_case("VEVENT") => {
+                    let event = current.take().unwrap();
+                    if let Err(e) = import.add(event, &mut tzids) {
+                        warn!("skipping event: {}", e);
+                    }
+                }
+                ("SUMMARY", Some(event)) => event.summary = Some(unescape(property.value)),
+                ("DTSTART", Some(event)) => event.start = Some(property),
+                ("DTEND", Some(event)) => event.end = Some(property),
+                ("RRULE", Some(event)) => event.rrule = Some(property.value),
+                ("EXDATE", Some(event)) => event.exdate = true,
+                _ => {}
+            }
+        }
+
+        match tzids.len() {
+            0 => {}
+            1 => import.timezone = tzids.into_iter().next(),
+            _ => warn!(
+                "events use several time zones ({}), times are kept as written",
+                tzids.into_iter().collect::<Vec<_>>().join(", ")
+            ),
+        }
+        for entries in import.timetable.values_mut() {
+            entries.sort_by_key(|entry| entry.time);
+        }
+        import.once.sort_by_key(|once| (once.date, once.time));
+
+        Ok(import)
+    }
+
+    fn add(&mut self, event: VEvent, tzids: &mut BTreeSet<String>) -> Result<(), String> {
+        let summary = event.summary.ok_or("event without a SUMMARY")?;
+        let start = event
+            .start
+            .as_ref()
+            .ok_or_else(|| format!("{}: no DTSTART", summary))
+            .and_then(|start| Moment::parse(start).map_err(|e| format!("{}: {}", summary, e)))?;
+        let end_time = match &event.end {
+            Some(end) => match Moment::parse(end) {
+                // the config can't express events lasting a day or more.
+                Ok(end) if end.at > start.at && end.at - start.at < Duration::days(1) => {
+                    Some(end.at.time())
+                }
+                _ => {
+                    warn!(
+                        "{}: ignoring an end that isn't within a day of its start",
+                        summary
+                    );
+                    None
+                }
+            },
+            None => None,
+        };
+        if let Some(tzid) = start.tzid {
+            tzids.insert(tzid);
+        }
+
+        match event.rrule {
+            Some(rrule) => {
+                let (days, interval) = weekly(rrule, start.at.weekday(), &summary)
+                    .map_err(|e| format!("{}: {}", summary, e))?;
+                if event.exdate {
+                    warn!(
+                        "{}: ignoring its excluded dates (EXDATE), add them to holidays if needed",
+                        summary
+                    );
+                }
+                for day in days {
+                    self.timetable
+                        .entry(day.to_string().to_lowercase())
+                        .or_default()
+                        .push(Entry {
+                            time: start.at.time(),
+                            end_time,
+                            event: summary.clone(),
+                            interval_weeks: (interval > 1).then_some(interval),
+                            anchor_date: (interval > 1).then_some(start.at.date()),
+                        });
+                }
+            }
+            None => self.once.push(OnceEntry {
+                date: start.at.date(),
+                time: start.at.time(),
+                end_time,
+                event: summary.clone(),
+            }),
+        }
+
+        self.events.entry(summary).or_default();
+        Ok(())
+    }
+
+    /// The generated config, with the events left to be mapped to commands.
+    pub fn to_toml(&self) -> Result<String, toml::ser::Error> {
+        Ok(format!(
+            "# imported by `eventjoiner import-ical`. Add the commands to launch and map each\n\
+             # event to them under `events`.\n\n{}",
+            toml::to_string(self)?
+        ))
+    }
+}
+
+/// Escape a text value.
+fn escape(value: &str) -> String {
+    let mut out = String::new();
+    for c in value.chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\n' => out.push_str("\\n"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// The `BYDAY` code of `day`.
+fn weekday_code(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+/// An offset from UTC in seconds as `+HHMM`.
+fn utc_offset(seconds: i32) -> String {
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let minutes = seconds.unsigned_abs() / 60;
+    format!("{}{:02}{:02}", sign, minutes / 60, minutes % 60)
+}
+
+/// Fold `line` into lines of at most 75 bytes, as the spec wants, and end it with CRLF.
+fn fold(out: &mut String, line: &str) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+/// The calendar for `config`'s timetable and one-off events. Weekly events recur from
+/// `valid_from`, or else from this week, until `valid_until` if set and skip the holidays.
+pub fn export(config: &Config) -> String {
+    let mut export = Export {
+        config,
+        out: String::new(),
+        tzid: match &config.timezone {
+            Some(name) => format!(";TZID={}", name),
+            None => String::new(),
+        },
+        stamp: Utc::now().format("%Y%m%dT%H%M%SZ").to_string(),
+        count: 0,
+    };
+
+    export.line("BEGIN:VCALENDAR");
+    export.line("VERSION:2.0");
+    export.line("PRODID:-//eventjoiner//eventjoiner//EN");
+    export.line("CALSCALE:GREGORIAN");
+
+    let today = config.now().date().naive_local();
+    let base = config
+        .valid_from
+        .unwrap_or_else(|| config.week_start(today));
+    if let Some(name) = &config.timezone {
+        export.timezone(name, base.year());
+    }
+    for day in Day::ALL {
+        for event in config.weekly_events(day) {
+            if event.enabled {
+                export.weekly(day, event, base);
+            }
+        }
+    }
+    for once in &config.once {
+        if once.event.enabled && config.is_valid_on(once.date) {
+            export.event(&once.event, once.date, None);
+        }
+    }
+
+    export.line("END:VCALENDAR");
+    export.out
+}
+
+/// A calendar being written out.
+struct Export<'a> {
+    config: &'a Config,
+    out: String,
+    /// `;TZID=...` for date-times, empty for floating local times.
+    tzid: String,
+    stamp: String,
+    count: usize,
+}
+
+impl<'a> Export<'a> {
+    fn line(&mut self, line: &str) {
+        fold(&mut self.out, line);
+    }
+
+    fn date_time(&self, date: NaiveDate, time: NaiveTime) -> String {
+        format!(
+            "{}:{}",
+            self.tzid,
+            date.and_time(time).format("%Y%m%dT%H%M%S")
+        )
+    }
+
+    /// The `VTIMEZONE` our `TZID`s refer to, so calendars read the times the way we do. Each
+    /// change of offset during `year` is taken to recur on the same weekday of its month
+    /// every year, as they do in practically every zone.
+    fn timezone(&mut self, name: &str, year: i32) {
+        let zone = self.config.zone;
+        let offset_at = |utc: i64| {
+            zone.offset_from_utc_datetime(&NaiveDateTime::from_timestamp(utc, 0))
+                .fix()
+                .local_minus_utc()
+        };
+
+        // look for changes a day at a time, then narrow each down to the second.
+        let mut changes = Vec::new();
+        let mut day = NaiveDate::from_ymd(year, 1, 1).and_hms(0, 0, 0).timestamp();
+        let end = NaiveDate::from_ymd(year + 1, 1, 1)
+            .and_hms(0, 0, 0)
+            .timestamp();
+        while day < end {
+            let (from, to) = (offset_at(day), offset_at(day + 86400));
+            if from != to {
+                let (mut before, mut after) = (day, day + 86400);
+                while after - before > 1 {
+                    let middle = before + (after - before) / 2;
+                    if offset_at(middle) == from {
+                        before = middle;
+                    } else {
+                        after = middle;
+                    }
+                }
+                changes.push((after, from, to));
+            }
+            day += 86400;
+        }
+
+        self.line("BEGIN:VTIMEZONE");
+        self.line(&format!("TZID:{}", name));
+        if changes.is_empty() {
+            let offset = utc_offset(offset_at(day));
+            self.line("BEGIN:STANDARD");
+            self.line(&format!("DTSTART:{}0101T000000", year));
+            self.line(&format!("TZOFFSETFROM:{}", offset));
+            self.line(&format!("TZOFFSETTO:{}", offset));
+            self.line("END:STANDARD");
+        }
+        for (at, from, to) in changes {
+            // given as the wall time it happens at before the change.
+            let local = NaiveDateTime::from_timestamp(at + from as i64, 0);
+            let date = local.date();
+            let next_month = match date.month() {
+                12 => NaiveDate::from_ymd(date.year() + 1, 1, 1),
+                month => NaiveDate::from_ymd(date.year(), month + 1, 1),
+            };
+            let last_day = next_month.pred().day();
+            let nth = if date.day() + 7 > last_day {
+                -1
+            } else {
+                (date.day() as i32 - 1) / 7 + 1
+            };
+            let kind = if to > from { "DAYLIGHT" } else { "STANDARD" };
+
+            self.line(&format!("BEGIN:{}", kind));
+            self.line(&format!("DTSTART:{}", local.format("%Y%m%dT%H%M%S")));
+            self.line(&format!("TZOFFSETFROM:{}", utc_offset(from)));
+            self.line(&format!("TZOFFSETTO:{}", utc_offset(to)));
+            self.line(&format!(
+                "RRULE:FREQ=YEARLY;BYMONTH={};BYDAY={}{}",
+                date.month(),
+                nth,
+                weekday_code(date.weekday())
+            ));
+            self.line(&format!("END:{}", kind));
+        }
+        self.line("END:VTIMEZONE");
+    }
+
+    /// A weekly `event` on `day`, starting with its first occurrence from `base` on.
+    fn weekly(&mut self, day: Day, event: &Event, base: NaiveDate) {
+        let interval = match (event.interval_weeks.unwrap_or(1), event.week_parity) {
+            (weeks, Some(_)) if weeks % 2 == 1 => weeks * 2,
+            (weeks, _) => weeks,
+        };
+        // `weeks` narrows the recurrence down to that range of semester weeks.
+        let (base, until) = match (event.weeks, self.config.valid_from) {
+            (Some(weeks), Some(from)) => {
+                let from = self.config.week_start(from);
+                let start = from + Duration::weeks(weeks.first as i64 - 1);
+                let end = from + Duration::weeks(weeks.last as i64) - Duration::days(1);
+                let until = self.config.valid_until.map_or(end, |until| until.min(end));
+                (base.max(start), Some(until))
+            }
+            _ => (base, self.config.valid_until),
+        };
+        let first = (0..7 * interval as i64)
+            .map(|days| base + Duration::days(days))
+            .find(|&date| Day::from(date.weekday()) == day && event.occurs_in_week_of(date));
+        let first = match first {
+            Some(first) if until.is_none_or(|until| first <= until) => first,
+            _ => return,
+        };
+
+        let mut rrule = "RRULE:FREQ=WEEKLY".to_string();
+        if interval > 1 {
+            write!(rrule, ";INTERVAL={}", interval).unwrap();
+        }
+        if let Some(until) = until {
+            // with a time zone the end has to be given in UTC.
+            let until = self.config.at(until, NaiveTime::from_hms(23, 59, 59));
+            write!(
+                rrule,
+                ";UNTIL={}",
+                until.with_timezone(&Utc).format("%Y%m%dT%H%M%SZ")
+            )
+            .unwrap();
+        }
+
+        let mut extra = vec![rrule];
+        for holiday in &self.config.holidays {
+            if first <= *holiday && Day::from(holiday.weekday()) == day {
+                extra.push(format!("EXDATE{}", self.date_time(*holiday, event.time)));
+            }
+        }
+
+        self.event(event, first, Some(extra));
+    }
+
+    fn event(&mut self, event: &Event, date: NaiveDate, extra: Option<Vec<String>>) {
+        self.count += 1;
+        let launches: Vec<String> = self
+            .config
+            .commands_for(event)
+            .iter()
+            .map(|command| command.to_string().trim_end().to_string())
+            .collect();
+
+        let mut lines = vec![
+            "BEGIN:VEVENT".to_string(),
+            format!(
+                "UID:{}-{}-{}@eventjoiner",
+                date.format("%Y%m%d"),
+                event.time.format("%H%M%S"),
+                self.count
+            ),
+            format!("DTSTAMP:{}", self.stamp),
+            format!("DTSTART{}", self.date_time(date, event.time)),
+        ];
+        if let Some(end_time) = event.end_time {
+            // ending before it starts means the next day.
+            let end_date = if end_time < event.time {
+                date.succ()
+            } else {
+                date
+            };
+            lines.push(format!("DTEND{}", self.date_time(end_date, end_time)));
+        }
+        lines.extend(extra.into_iter().flatten());
+        lines.push(format!("SUMMARY:{}", escape(&event.event)));
+        if launches.is_empty() {
+            lines.push("DESCRIPTION:launches nothing".to_string());
+        } else {
+            lines.push(format!(
+                "DESCRIPTION:{}",
+                escape(&format!("launches:\n{}", launches.join("\n")))
+            ));
+        }
+        lines.push("END:VEVENT".to_string());
+
+        for line in lines {
+            self.line(&line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `lines` wrapped in a calendar, with CRLF line endings as in real files.
+    fn calendar(lines: &[&str]) -> String {
+        format!(
+            "BEGIN:VCALENDAR\r\nVERSION:2.0\r\n{}\r\nEND:VCALENDAR\r\n",
+            lines.join("\r\n")
+        )
+    }
+
+    fn date(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    fn time(text: &str) -> NaiveTime {
+        NaiveTime::parse_from_str(text, "%H:%M").unwrap()
+    }
+
+    #[test]
+    fn weekly_rrule_with_byday_and_interval() {
+        let import = Import::from_ical(&calendar(&[
+            "BEGIN:VEVENT",
+            "SUMMARY:Maths",
+            "DTSTART;TZID=Europe/Berlin:20240108T090000",
+            "DTEND;TZID=Europe/Berlin:20240108T103000",
+            "RRULE:FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,TH",
+            "END:VEVENT",
+        ]))
+        .unwrap();
+
+        assert_eq!(import.timezone.as_deref(), Some("Europe/Berlin"));
+        assert!(import.events.contains_key("Maths"));
+        for day in ["mon", "thu"] {
+            let entries = &import.timetable[day];
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries[0].time, time("09:00"));
+            assert_eq!(entries[0].end_time, Some(time("10:30")));
+            assert_eq!(entries[0].interval_weeks, Some(2));
+            assert_eq!(entries[0].anchor_date, Some(date("2024-01-08")));
+        }
+        assert_eq!(import.timetable.len(), 2);
+        assert!(import.once.is_empty());
+    }
+
+    #[test]
+    fn one_off_event() {
+        let import = Import::from_ical(&calendar(&[
+            "BEGIN:VEVENT",
+            "SUMMARY:Exam",
+            "DTSTART:20240212T140000",
+            "DTEND:20240212T160000",
+            "END:VEVENT",
+        ]))
+        .unwrap();
+
+        assert!(import.timetable.is_empty());
+        assert_eq!(import.once.len(), 1);
+        assert_eq!(import.once[0].date, date("2024-02-12"));
+        assert_eq!(import.once[0].time, time("14:00"));
+        assert_eq!(import.once[0].end_time, Some(time("16:00")));
+        assert_eq!(import.once[0].event, "Exam");
+    }
+
+    #[test]
+    fn daily_recurrence_is_skipped() {
+        let import = Import::from_ical(&calendar(&[
+            "BEGIN:VEVENT",
+            "SUMMARY:Standup",
+            "DTSTART:20240108T093000",
+            "RRULE:FREQ=DAILY",
+            "END:VEVENT",
+        ]))
+        .unwrap();
+
+        assert!(import.timetable.is_empty());
+        assert!(import.once.is_empty());
+        assert!(import.events.is_empty());
+        // what the skipped event is warned about.
+        assert_eq!(
+            weekly("FREQ=DAILY", Weekday::Mon, "Standup").unwrap_err(),
+            "only weekly recurrence is supported, not DAILY"
+        );
+    }
+
+    #[test]
+    fn folded_lines_and_escaped_summary() {
+        let import = Import::from_ical(&calendar(&[
+            "BEGIN:VEVENT",
+            "SUMMARY:Algebra\\, Groups \\; Rings\\nand Fi",
+            "\telds",
+            "DTSTART:2024010",
+            " 8T090000",
+            "END:VEVENT",
+        ]))
+        .unwrap();
+
+        assert_eq!(import.once.len(), 1);
+        assert_eq!(import.once[0].event, "Algebra, Groups ; Rings and Fields");
+        assert_eq!(import.once[0].date, date("2024-01-08"));
+        assert_eq!(import.once[0].time, time("09:00"));
+    }
+
+    #[test]
+    fn utc_start_moves_to_local_time() {
+        let import = Import::from_ical(&calendar(&[
+            "BEGIN:VEVENT",
+            "SUMMARY:Call",
+            "DTSTART:20240108T090000Z",
+            "END:VEVENT",
+        ]))
+        .unwrap();
+
+        let local = Utc
+            .from_utc_datetime(&date("2024-01-08").and_time(time("09:00")))
+            .with_timezone(&Local)
+            .naive_local();
+        assert_eq!(import.timezone, None);
+        assert_eq!(import.once[0].date, local.date());
+        assert_eq!(import.once[0].time, local.time());
+    }
+
+    #[test]
+    fn export_in_a_named_zone() {
+        let config = Config::from_toml(&format!(
+            "version = {}
+            default_command = 'nothing'
+            notify_before = 5
+            timezone = 'Europe/Berlin'
+            valid_from = '2024-01-08'
+            valid_until = '2024-04-05'
+            holidays = ['2024-01-22', '2024-01-23']
+            [timetable]
+            mon = [{{ time = '23:00:00', end_time = '01:30:00', interval_weeks = 2, \
+                anchor_date = '2024-01-08', event = 'night lab, where the telescopes are \
+                calibrated before the observing run starts' }}]
+            [command.nothing]
+            name = 'true'",
+            eventjoiner::CONFIG_VERSION
+        ))
+        .unwrap();
+        let calendar = export(&config);
+
+        assert!(calendar.ends_with("\r\n"));
+        assert!(calendar.split("\r\n").all(|line| line.len() <= 75));
+        assert!(calendar.contains("\r\n "));
+
+        let lines = unfold(&calendar.replace("\r\n", "\n"));
+        let timezone = [
+            "BEGIN:VTIMEZONE",
+            "TZID:Europe/Berlin",
+            "BEGIN:DAYLIGHT",
+            "DTSTART:20240331T020000",
+            "TZOFFSETFROM:+0100",
+            "TZOFFSETTO:+0200",
+            "RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU",
+            "END:DAYLIGHT",
+            "BEGIN:STANDARD",
+            "DTSTART:20241027T030000",
+            "TZOFFSETFROM:+0200",
+            "TZOFFSETTO:+0100",
+            "RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU",
+            "END:STANDARD",
+            "END:VTIMEZONE",
+        ];
+        let begin = lines
+            .iter()
+            .position(|line| line == "BEGIN:VTIMEZONE")
+            .unwrap();
+        assert_eq!(lines[begin..begin + timezone.len()], timezone);
+
+        let event: Vec<&str> = lines
+            .iter()
+            .map(String::as_str)
+            .skip_while(|line| *line != "BEGIN:VEVENT")
+            .filter(|line| !line.starts_with("UID:") && !line.starts_with("DTSTAMP:"))
+            .collect();
+        assert_eq!(
+            event,
+            [
+                "BEGIN:VEVENT",
+                "DTSTART;TZID=Europe/Berlin:20240108T230000",
+                "DTEND;TZID=Europe/Berlin:20240109T013000",
+                "RRULE:FREQ=WEEKLY;INTERVAL=2;UNTIL=20240405T215959Z",
+                "EXDATE;TZID=Europe/Berlin:20240122T230000",
+                "SUMMARY:night lab\\, where the telescopes are calibrated before the \
+                 observing run starts",
+                "DESCRIPTION:launches:\\ntrue",
+                "END:VEVENT",
+                "END:VCALENDAR",
+            ]
+        );
+    }
+}
@@ -0,0 +1,508 @@
+//! A minimal serde serializer producing compact JSON, enough for the `--json` output.
+
+use std::fmt::{self, Display, Write};
+
+use serde::ser::{self, Serialize};
+
+#[derive(Debug)]
+pub struct Error(String);
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+/// Serialize `value` as a JSON string.
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String, Error> {
+    let mut serializer = Serializer { out: String::new() };
+    value.serialize(&mut serializer)?;
+    Ok(serializer.out)
+}
+
+struct Serializer {
+    out: String,
+}
+
+impl Serializer {
+    fn write_str(&mut self, v: &str) {
+        self.out.push('"');
+        for c in v.chars() {
+            match c {
+                '"' => self.out.push_str("\\\""),
+                '\\' => self.out.push_str("\\\\"),
+                '\n' => self.out.push_str("\\n"),
+                '\r' => self.out.push_str("\\r"),
+                '\t' => self.out.push_str("\\t"),
+                c if (c as u32) < 0x20 => {
+                    let _ = write!(self.out, "\\u{:04x}", c as u32);
+                }
+                c => self.out.push(c),
+            }
+        }
+        self.out.push('"');
+    }
+
+    fn write_display<T: Display>(&mut self, v: T) {
+        let _ = write!(self.out, "{}", v);
+    }
+}
+
+/// Tracks whether a separator is needed before the next element of a sequence or map.
+pub struct Compound<'a> {
+    ser: &'a mut Serializer,
+    first: bool,
+    /// closing text to emit when done, e.g. `]` or `]}` for variants.
+    close: &'static str,
+}
+
+impl Compound<'_> {
+    fn separate(&mut self) {
+        if !self.first {
+            self.ser.out.push(',');
+        }
+        self.first = false;
+    }
+}
+
+impl<'a> ser::Serializer for &'a mut Serializer {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Compound<'a>;
+    type SerializeTuple = Compound<'a>;
+    type SerializeTupleStruct = Compound<'a>;
+    type SerializeTupleVariant = Compound<'a>;
+    type SerializeMap = Compound<'a>;
+    type SerializeStruct = Compound<'a>;
+    type SerializeStructVariant = Compound<'a>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), Error> {
+        self.write_display(v);
+        Ok(())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), Error> {
+        self.write_display(v);
+        Ok(())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), Error> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), Error> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), Error> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), Error> {
+        self.write_display(v);
+        Ok(())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), Error> {
+        if v.is_finite() {
+            self.write_display(v);
+        } else {
+            self.out.push_str("null");
+        }
+        Ok(())
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), Error> {
+        self.write_str(v.encode_utf8(&mut [0; 4]));
+        Ok(())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), Error> {
+        self.write_str(v);
+        Ok(())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+        use ser::SerializeSeq;
+
+        let mut seq = self.serialize_seq(Some(v.len()))?;
+        for byte in v {
+            seq.serialize_element(byte)?;
+        }
+        seq.end()
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        self.out.push_str("null");
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<(), Error> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.out.push('{');
+        self.write_str(variant);
+        self.out.push(':');
+        value.serialize(&mut *self)?;
+        self.out.push('}');
+        Ok(())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Compound<'a>, Error> {
+        self.out.push('[');
+        Ok(Compound {
+            ser: self,
+            first: true,
+            close: "]",
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Compound<'a>, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Compound<'a>, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>, Error> {
+        self.out.push('{');
+        self.write_str(variant);
+        self.out.push_str(":[");
+        Ok(Compound {
+            ser: self,
+            first: true,
+            close: "]}",
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Compound<'a>, Error> {
+        self.out.push('{');
+        Ok(Compound {
+            ser: self,
+            first: true,
+            close: "}",
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Compound<'a>, Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>, Error> {
+        self.out.push('{');
+        self.write_str(variant);
+        self.out.push_str(":{");
+        Ok(Compound {
+            ser: self,
+            first: true,
+            close: "}}",
+        })
+    }
+}
+
+impl ser::SerializeSeq for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.separate();
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.ser.out.push_str(self.close);
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleVariant for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeMap for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        self.separate();
+        // JSON keys must be strings, so render whatever we get and quote it.
+        let key = to_string(key)?;
+        if key.starts_with('"') {
+            self.ser.out.push_str(&key);
+        } else {
+            self.ser.write_str(&key);
+        }
+        self.ser.out.push(':');
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeStruct for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        ser::SerializeMap::serialize_entry(self, key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeStructVariant for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        ser::SerializeMap::serialize_entry(self, key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use chrono::{FixedOffset, SecondsFormat, TimeZone};
+    use eventjoiner::CommandArgs;
+    use serde::Serialize;
+
+    use super::to_string;
+    use crate::NextOutput;
+
+    #[test]
+    fn strings_are_escaped() {
+        assert_eq!(
+            to_string("say \"hi\" \\ bye").unwrap(),
+            r#""say \"hi\" \\ bye""#
+        );
+        assert_eq!(
+            to_string("a\nb\tc\r\u{1}\u{1f}").unwrap(),
+            r#""a\nb\tc\r\u0001\u001f""#
+        );
+        assert_eq!(to_string(&'"').unwrap(), r#""\"""#);
+    }
+
+    #[test]
+    fn none_and_unit_are_null() {
+        assert_eq!(to_string(&None::<u32>).unwrap(), "null");
+        assert_eq!(to_string(&Some(3)).unwrap(), "3");
+        assert_eq!(to_string(&()).unwrap(), "null");
+        assert_eq!(to_string(&f64::NAN).unwrap(), "null");
+    }
+
+    #[test]
+    fn nested_structs_sequences_and_maps() {
+        #[derive(Serialize)]
+        struct Inner {
+            on: bool,
+            tags: Vec<&'static str>,
+        }
+        #[derive(Serialize)]
+        struct Outer {
+            id: i32,
+            inner: Inner,
+            counts: BTreeMap<&'static str, Vec<u8>>,
+            empty: Vec<u8>,
+        }
+
+        let value = Outer {
+            id: -1,
+            inner: Inner {
+                on: true,
+                tags: vec!["a", "b"],
+            },
+            counts: BTreeMap::from([("x", vec![1, 2]), ("y", vec![])]),
+            empty: vec![],
+        };
+        assert_eq!(
+            to_string(&value).unwrap(),
+            r#"{"id":-1,"inner":{"on":true,"tags":["a","b"]},"counts":{"x":[1,2],"y":[]},"empty":[]}"#
+        );
+    }
+
+    #[test]
+    fn non_string_map_keys_are_quoted() {
+        assert_eq!(
+            to_string(&BTreeMap::from([(1, "one"), (20, "twenty")])).unwrap(),
+            r#"{"1":"one","20":"twenty"}"#
+        );
+        assert_eq!(
+            to_string(&BTreeMap::from([(true, 1)])).unwrap(),
+            r#"{"true":1}"#
+        );
+        assert_eq!(
+            to_string(&BTreeMap::from([('"', 1)])).unwrap(),
+            r#"{"\"":1}"#
+        );
+    }
+
+    #[test]
+    fn enum_variants() {
+        #[derive(Serialize)]
+        enum Shape {
+            Empty,
+            Circle(u32),
+            Line(i32, i32),
+            Rect { w: u32, h: u32 },
+        }
+
+        assert_eq!(to_string(&Shape::Empty).unwrap(), r#""Empty""#);
+        assert_eq!(to_string(&Shape::Circle(2)).unwrap(), r#"{"Circle":2}"#);
+        assert_eq!(
+            to_string(&Shape::Line(1, -1)).unwrap(),
+            r#"{"Line":[1,-1]}"#
+        );
+        assert_eq!(
+            to_string(&Shape::Rect { w: 3, h: 4 }).unwrap(),
+            r#"{"Rect":{"w":3,"h":4}}"#
+        );
+    }
+
+    #[test]
+    fn next_output() {
+        let command: CommandArgs =
+            toml::from_str("name = 'firefox'\nargs = ['--new-window', 'https://meet.example/x']")
+                .unwrap();
+        let start = FixedOffset::east(3600).ymd(2024, 1, 1).and_hms(9, 0, 0);
+        let output = NextOutput {
+            event: "maths",
+            start: start.to_rfc3339_opts(SecondsFormat::Secs, false),
+            seconds_until: 300,
+            commands: vec![&command],
+        };
+        assert_eq!(
+            to_string(&output).unwrap(),
+            r#"{"event":"maths","start":"2024-01-01T09:00:00+01:00","seconds_until":300,"commands":[{"name":"firefox","args":["--new-window","https://meet.example/x"]}]}"#
+        );
+    }
+}
@@ -0,0 +1,2670 @@
+//! The timetable and the scheduling logic of eventjoiner: parsing the config, working out
+//! which event comes next and launching its commands.
+
+use std::{
+    collections::HashMap,
+    env, error,
+    ffi::OsStr,
+    fmt,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    os::unix::{fs::PermissionsExt, process::CommandExt},
+    path::Path,
+    process::{Command, ExitStatus, Stdio},
+    str::FromStr,
+    sync::Once,
+    thread,
+    time::Duration as StdDuration,
+};
+
+use chrono::{
+    DateTime, Datelike, Duration, Local, NaiveDate, NaiveTime, Offset, SecondsFormat, TimeZone,
+    Timelike, Utc,
+};
+use serde::{Deserialize, Serialize};
+
+pub mod tz;
+
+use tz::Zone;
+
+/// The shape of config this version of eventjoiner reads. Older ones are migrated to it
+/// when loaded, a config without a `version` is version 0.
+pub const CONFIG_VERSION: u32 = 2;
+
+/// The config as read from the config file.
+#[derive(Debug, Deserialize, Clone)]
+pub struct Config {
+    /// Which shape of config the file is written in, see `CONFIG_VERSION`.
+    #[serde(default)]
+    pub version: u32,
+    /// Maps weekdays to  vectors of scheduled events for that day. Keys naming the same
+    /// day, like `mon` and `monday`, have their events merged.
+    #[serde(default, deserialize_with = "merge_days")]
+    pub timetable: HashMap<Day, Vec<Event>>,
+    /// Maps a particular event to the command names to run when it's time for that event,
+    /// written as a single name or a list of them. Can be left out with a `default_command`.
+    /// Names can be globs using `*` and `?`, see event_mapping for which one wins.
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub events: HashMap<String, Vec<String>>,
+    /// Maps command names to actual command.
+    #[serde(default)]
+    pub command: HashMap<String, CommandArgs>,
+    /// The command to run for events that have no entry in `events`.
+    #[serde(default)]
+    pub default_command: Option<String>,
+    /// How much time before notifying for event, in minutes or as a duration like `"90s"`.
+    pub notify_before: ConfigDuration,
+    /// Events that only happen on a particular date, on top of the weekly timetable.
+    #[serde(default)]
+    pub once: Vec<OnceEvent>,
+    /// No events happen before this date, e.g. the start of a semester.
+    #[serde(default)]
+    pub valid_from: Option<NaiveDate>,
+    /// No events happen after this date, e.g. the end of a semester.
+    #[serde(default)]
+    pub valid_until: Option<NaiveDate>,
+    /// Dates on which no events happen at all.
+    #[serde(default)]
+    pub holidays: Vec<NaiveDate>,
+    /// IANA time zone the timetable is written in, e.g. `Europe/Berlin`. Defaults to the
+    /// system's local time zone.
+    #[serde(default)]
+    pub timezone: Option<String>,
+    /// The day weeks start on for `--list-all` and anything else about "this week". Weeks
+    /// for `week_parity` and `interval_weeks` always start on Monday.
+    #[serde(default = "default_week_start")]
+    pub week_starts_on: Day,
+    /// For how long after it started an event still counts as current, in minutes or as a
+    /// duration like `"90s"`.
+    #[serde(default = "default_grace")]
+    pub grace_after: ConfigDuration,
+    /// The least time between two launches by the daemon, in minutes or as a duration like
+    /// `"90s"`. A notification due sooner is put off, but never past the event's start.
+    #[serde(default)]
+    pub min_gap: Option<ConfigDuration>,
+    /// Expand unset environment variables in commands to nothing, with a warning, instead of
+    /// refusing the config.
+    #[serde(default)]
+    pub allow_unset_env: bool,
+    /// Whether the daemon sends a notification when it launches an event.
+    #[serde(default = "default_true")]
+    pub notifications: bool,
+    /// Whether the daemon launches an event's commands when it comes up. Without, it only
+    /// sends a notification to remind of it.
+    #[serde(default = "default_true")]
+    pub auto_launch: bool,
+    /// Whether the daemon splits each event in two: a heads-up notification `notify_before`
+    /// it, then launching its commands, and notifying of that, as it starts.
+    #[serde(default)]
+    pub pre_alert: bool,
+    /// Whether the daemon sends a notification listing each day's events as the day starts,
+    /// or when it first wakes up that day.
+    #[serde(default)]
+    pub daily_summary: bool,
+    /// Other timetables to use instead of the top-level one, like one for exam weeks.
+    #[serde(default)]
+    pub profiles: HashMap<String, Profile>,
+    /// The profile to use unless another one is selected. Without one the top-level
+    /// timetable is used.
+    #[serde(default)]
+    pub default_profile: Option<String>,
+    /// What was changed to bring an older config up to `CONFIG_VERSION`, for logging.
+    #[serde(skip)]
+    pub migrated: Vec<String>,
+    /// The zone named by `timezone`, resolved once the config is loaded.
+    #[serde(skip)]
+    pub zone: Zone,
+    /// Pretend it's this time instead of asking the system clock.
+    #[serde(skip)]
+    pub clock: Option<DateTime<Zone>>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_week_start() -> Day {
+    Day::Monday
+}
+
+fn default_grace() -> ConfigDuration {
+    ConfigDuration(Duration::zero())
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
+
+/// Represents a command to launch when time for event.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct CommandArgs {
+    /// Name of the binary to run. Placeholders are filled in as in `args`.
+    #[serde(default)]
+    pub name: String,
+    /// Arguments to pass to that binary. `{event}`, `{date}`, `{time}` and `{weekday}` are
+    /// replaced with the event's name, its start date and time, and its day of the week.
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Another command to take `name` from, its `args` going before these, so `name` must
+    /// be left out. Resolved when the config is loaded, so it's always unset afterwards.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+    /// Extra environment variables to set for the binary.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub env: HashMap<String, String>,
+    /// Directory to run the binary in. `~` and `$VAR`s are expanded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    /// Join `name` and `args` with spaces and run the result through `sh -c`, so pipes,
+    /// globs and `&&` work. Nothing is quoted, the shell sees the line exactly as joined.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub shell: bool,
+    /// Body of the notification sent by the daemon. `{event}` and `{time}` are replaced
+    /// with the event's name and start time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notify_body: Option<String>,
+    /// Urgency of the notification sent by the daemon.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notify_urgency: Option<Urgency>,
+    /// How long the notification stays up, in milliseconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notify_timeout_ms: Option<u32>,
+    /// Icon of the notification, a freedesktop icon name or a path to an image.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    /// How many more times the daemon tries to spawn the binary if it fails to.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub retries: u32,
+    /// How long to wait before the first retry, in minutes or as a duration like `"30s"`,
+    /// doubling after each one. Defaults to 5 seconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_delay: Option<ConfigDuration>,
+    /// Append the binary's stdout and stderr to this file instead of inheriting ours, after
+    /// a header with the time it was launched at. `~` and `$VAR`s are expanded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_output: Option<String>,
+    /// Don't launch the binary while a process running it is alive, e.g. so reloading the
+    /// daemon doesn't open a second meeting window. Goes by the binary's name only.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub singleton: bool,
+    /// When a process running the binary was already alive, bring its window to the front
+    /// with `wmctrl` after launching, e.g. for a browser that hands the meeting to the window
+    /// it has open. Nothing more happens without `wmctrl`, or if it finds no window.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub reuse_window: bool,
+    /// Start the binary in a session of its own, so it outlives the daemon and isn't
+    /// stopped along with it, e.g. by a terminal hanging up.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub detach: bool,
+    /// How long from the event's start to launch the binary at, negative for before it,
+    /// rather than along with the daemon's notification. Never earlier than that.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub launch_offset: Option<LaunchOffset>,
+    /// Run before the binary is launched, which waits for it to exit successfully.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before: Option<Box<CommandArgs>>,
+    /// Launched once the binary has been, without waiting for either.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<Box<CommandArgs>>,
+}
+
+/// How urgent a notification is.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Urgency {
+    Low,
+    Normal,
+    Critical,
+}
+
+/// A particular event in a day.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct Event {
+    /// At which hour (from 0 to 23) does the event occur.
+    pub time: NaiveTime,
+    /// The event to launch at this event.
+    pub event: String,
+    /// When the event ends, if known. An end before `time` is on the next day.
+    #[serde(default)]
+    pub end_time: Option<NaiveTime>,
+    /// Overrides the global `notify_before` for this event.
+    #[serde(default)]
+    pub notify_before: Option<ConfigDuration>,
+    /// Overrides the global `auto_launch` for this event.
+    #[serde(default)]
+    pub auto_launch: Option<bool>,
+    /// Overrides the global `pre_alert` for this event.
+    #[serde(default)]
+    pub pre_alert: Option<bool>,
+    /// Overrides the `launch_offset` of each of the event's commands.
+    #[serde(default)]
+    pub launch_offset: Option<LaunchOffset>,
+    /// Only happen in odd or even ISO weeks.
+    #[serde(default)]
+    pub week_parity: Option<WeekParity>,
+    /// Only happen every this many weeks, counting from the week of `anchor_date`.
+    #[serde(default)]
+    pub interval_weeks: Option<u32>,
+    /// A date in one of the weeks the event happens, for `interval_weeks`.
+    #[serde(default)]
+    pub anchor_date: Option<NaiveDate>,
+    /// Only happen in these weeks of the semester, the week of `valid_from` being week 1.
+    #[serde(default)]
+    pub weeks: Option<Weeks>,
+    /// A disabled event stays in the timetable but is never launched.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// The command to launch for this event, taking the place of its entry in `events`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<CommandArgs>,
+}
+
+/// Which ISO week numbers an event happens in.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WeekParity {
+    Odd,
+    Even,
+}
+
+/// A range of semester weeks, written `"1-6"` or as a single week like `"7"`.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub struct Weeks {
+    pub first: u32,
+    pub last: u32,
+}
+
+impl Weeks {
+    pub fn contains(&self, week: u32) -> bool {
+        self.first <= week && week <= self.last
+    }
+}
+
+impl TryFrom<String> for Weeks {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        let week = |part: &str| part.trim().parse::<u32>().ok().filter(|&week| week > 0);
+        let (first, last) = match text.split_once('-') {
+            Some((first, last)) => (week(first), week(last)),
+            None => (week(&text), week(&text)),
+        };
+        match (first, last) {
+            (Some(first), Some(last)) if first <= last => Ok(Weeks { first, last }),
+            _ => Err(format!("invalid weeks {}, expected a range like 1-6", text)),
+        }
+    }
+}
+
+impl Serialize for Weeks {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{}-{}", self.first, self.last))
+    }
+}
+
+impl Event {
+    /// Whether a weekly event happens in the week of `date`, given its parity and interval.
+    pub fn occurs_in_week_of(&self, date: NaiveDate) -> bool {
+        let odd_week = date.iso_week().week() % 2 == 1;
+        let parity_matches = match self.week_parity {
+            Some(WeekParity::Odd) => odd_week,
+            Some(WeekParity::Even) => !odd_week,
+            None => true,
+        };
+
+        let interval_matches = match (self.interval_weeks, self.anchor_date) {
+            (Some(interval), Some(anchor)) if interval > 0 => {
+                let week_start = |date: NaiveDate| {
+                    date - Duration::days(date.weekday().num_days_from_monday() as i64)
+                };
+                let weeks = (week_start(date) - week_start(anchor)).num_days() / 7;
+                weeks.rem_euclid(interval as i64) == 0
+            }
+            _ => true,
+        };
+
+        parity_matches && interval_matches
+    }
+}
+
+/// What kind of problem `check` found.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProblemKind {
+    /// An event has no entry in `events` and there is no `default_command`.
+    MissingEventMapping,
+    /// `events` or `default_command` names a command that doesn't exist, or an event maps
+    /// to no commands at all.
+    MissingCommand,
+    /// Two events on the same day overlap.
+    Overlap,
+    /// A timetable key isn't a day.
+    UnknownDay,
+    /// A command's binary can't be found.
+    MissingBinary,
+    /// `valid_until` is before `valid_from`.
+    InvalidRange,
+    /// An event's time or end_time can't be meant that way.
+    InvalidTime,
+    /// An event's `interval_weeks` is 0 or has no `anchor_date`, or it has `weeks` without
+    /// a `valid_from`.
+    InvalidInterval,
+    /// The config couldn't be loaded at all.
+    Load,
+}
+
+/// Whether a problem makes a config unusable, or only looks like a mistake.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// Something wrong with the config, as reported by `validate`.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct Problem {
+    pub kind: ProblemKind,
+    pub severity: Severity,
+    /// Where in the config the problem is, like `events.history` or `timetable.monday`.
+    pub key: String,
+    pub message: String,
+}
+
+impl Problem {
+    /// A problem of `kind`, which decides its severity.
+    pub fn new(kind: ProblemKind, key: impl Into<String>, message: String) -> Self {
+        let severity = match kind {
+            // the binary might come from somewhere we can't see, and overlaps are only
+            // almost always a mistake.
+            ProblemKind::Overlap | ProblemKind::MissingBinary => Severity::Warning,
+            _ => Severity::Error,
+        };
+        Problem {
+            kind,
+            severity,
+            key: key.into(),
+            message,
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// The key of the events labelled `label` by labelled_events, `timetable.monday` for a day
+/// and `once` for a date.
+fn event_key(label: &str) -> String {
+    if label.parse::<NaiveDate>().is_ok() {
+        "once".to_string()
+    } else {
+        format!("timetable.{}", label.to_lowercase())
+    }
+}
+
+/// Where an event's own `command` is in the config, for messages about it.
+fn inline_place(label: &str, event: &Event) -> String {
+    format!("{} {} {}.command", label, event.time, event.event)
+}
+
+/// Whether `name` matches the glob `pattern`, where `*` stands for any run of characters
+/// and `?` for any single one.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    // where to resume after the last `*` when the rest stops matching.
+    let mut star = None;
+    let (mut p, mut n) = (0, 0);
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    star = Some((star_p, star_n + 1));
+                    p = star_p + 1;
+                    n = star_n + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Follow the `alias` of `command`, at `place` in the config, through `commands`, taking
+/// the name of the last command in the chain and putting each one's args before its own.
+/// Then the same for its hooks.
+fn resolve_alias(
+    command: &mut CommandArgs,
+    commands: &HashMap<String, CommandArgs>,
+    place: &str,
+) -> Result<(), ConfigError> {
+    if command.alias.is_some() && !command.name.is_empty() {
+        return Err(ConfigError::NameAndAlias(place.to_string()));
+    }
+    let mut chain = Vec::new();
+    while let Some(target) = command.alias.take() {
+        if chain.contains(&target) {
+            chain.push(target);
+            return Err(ConfigError::AliasCycle(place.to_string(), chain));
+        }
+        let base = commands
+            .get(&target)
+            .ok_or_else(|| ConfigError::UnknownAlias(place.to_string(), target.clone()))?;
+        command.name = base.name.clone();
+        command.args = base
+            .args
+            .iter()
+            .cloned()
+            .chain(std::mem::take(&mut command.args))
+            .collect();
+        command.alias = base.alias.clone();
+        chain.push(target);
+    }
+    if command.name.is_empty() {
+        return Err(ConfigError::MissingName(place.to_string()));
+    }
+
+    if let Some(before) = &mut command.before {
+        resolve_alias(before, commands, &format!("{}.before", place))?;
+    }
+    if let Some(after) = &mut command.after {
+        resolve_alias(after, commands, &format!("{}.after", place))?;
+    }
+    Ok(())
+}
+
+/// Drop every item that was already seen earlier on, wherever it is.
+fn dedup<T: PartialEq + Clone>(items: &mut Vec<T>) {
+    let mut seen = Vec::new();
+    items.retain(|item| {
+        let new = !seen.contains(item);
+        if new {
+            seen.push(item.clone());
+        }
+        new
+    });
+}
+
+/// Whether `name` is an executable file, looked up on `PATH` unless it names a path itself.
+fn binary_exists(name: &str) -> bool {
+    let executable = |path: &Path| {
+        path.metadata()
+            .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+    };
+
+    if name.contains('/') {
+        return executable(Path::new(name));
+    }
+    env::var_os("PATH")
+        .is_some_and(|path| env::split_paths(&path).any(|dir| executable(&dir.join(name))))
+}
+
+/// What's wrong with a time chrono accepts but a timetable can't mean: a leap second, as in
+/// `23:59:60`, or a fraction of a second.
+fn odd_time(time: NaiveTime) -> Option<&'static str> {
+    match time.nanosecond() {
+        0 => None,
+        1_000_000_000.. => Some("a leap second"),
+        _ => Some("a fraction of a second"),
+    }
+}
+
+/// Deserialize a map whose values are either a single string or a list of strings.
+fn one_or_many<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<HashMap<String, Vec<String>>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    let map = HashMap::<String, OneOrMany>::deserialize(deserializer)?;
+    Ok(map
+        .into_iter()
+        .map(|(key, value)| match value {
+            OneOrMany::One(name) => (key, vec![name]),
+            OneOrMany::Many(names) => (key, names),
+        })
+        .collect())
+}
+
+/// Upgrade a config written as `version` to `CONFIG_VERSION` one version at a time,
+/// returning what was changed.
+fn migrate(config: &mut toml::Value, version: u32) -> Vec<String> {
+    let mut migrated = Vec::new();
+
+    if version < 1 {
+        // version 0 spelled Tuesday as "teu", rename it in every timetable.
+        if let Some(timetable) = config.get_mut("timetable") {
+            rename_teu(timetable, "timetable", &mut migrated);
+        }
+        if let Some(profiles) = config
+            .get_mut("profiles")
+            .and_then(toml::Value::as_table_mut)
+        {
+            for (name, profile) in profiles {
+                if let Some(timetable) = profile.get_mut("timetable") {
+                    let label = format!("profiles.{}.timetable", name);
+                    rename_teu(timetable, &label, &mut migrated);
+                }
+            }
+        }
+    }
+
+    if version < 2 {
+        // version 1 gave retry delays as `retry_delay_secs`, a bare number of seconds, where
+        // every other duration is in minutes.
+        rename_retry_delay(config, "", &mut migrated);
+    }
+
+    migrated
+}
+
+/// Replace `retry_delay_secs` by the same `retry_delay` in every table under `value`, which
+/// is at the dotted key `label`.
+fn rename_retry_delay(value: &mut toml::Value, label: &str, migrated: &mut Vec<String>) {
+    let key = |key: &str| match label {
+        "" => key.to_string(),
+        label => format!("{}.{}", label, key),
+    };
+    match value {
+        toml::Value::Table(table) => {
+            if let Some(secs) = table.remove("retry_delay_secs") {
+                let delay = match secs.as_integer() {
+                    Some(secs) => toml::Value::String(format!("{}s", secs)),
+                    // not a number, leave it for deserializing to complain about.
+                    None => secs,
+                };
+                table.insert("retry_delay".to_string(), delay);
+                migrated.push(format!(
+                    "{} replaced by retry_delay",
+                    key("retry_delay_secs")
+                ));
+            }
+            for (name, value) in table.iter_mut() {
+                rename_retry_delay(value, &key(name), migrated);
+            }
+        }
+        toml::Value::Array(items) => {
+            for (idx, item) in items.iter_mut().enumerate() {
+                rename_retry_delay(item, &format!("{}[{}]", label, idx), migrated);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Move the events of a `teu` key in `timetable` to `tue`, appending them to any already
+/// there.
+fn rename_teu(timetable: &mut toml::Value, label: &str, migrated: &mut Vec<String>) {
+    let timetable = match timetable.as_table_mut() {
+        Some(timetable) => timetable,
+        None => return,
+    };
+    let keys: Vec<_> = timetable
+        .keys()
+        .filter(|key| key.trim().eq_ignore_ascii_case("teu"))
+        .cloned()
+        .collect();
+    for key in keys {
+        let events = match timetable.remove(&key) {
+            Some(events) => events,
+            None => continue,
+        };
+        match (timetable.get_mut("tue"), events) {
+            (Some(toml::Value::Array(tue)), toml::Value::Array(events)) => tue.extend(events),
+            (_, events) => {
+                timetable.insert("tue".to_string(), events);
+            }
+        }
+        migrated.push(format!("{}.{} renamed to tue", label, key));
+    }
+}
+
+/// Deserialize a timetable, appending the events of every key naming a day to that day
+/// rather than keeping only those of the last key, so `mon` and `monday` can both be used.
+fn merge_days<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<HashMap<Day, Vec<Event>>, D::Error> {
+    struct Days;
+
+    impl<'de> serde::de::Visitor<'de> for Days {
+        type Value = HashMap<Day, Vec<Event>>;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("a map of days to lists of events")
+        }
+
+        fn visit_map<A: serde::de::MapAccess<'de>>(
+            self,
+            mut map: A,
+        ) -> Result<Self::Value, A::Error> {
+            let mut days = HashMap::<Day, Vec<Event>>::new();
+            // in the order they're written, so events at the same time keep it.
+            while let Some((day, events)) = map.next_entry::<Day, Vec<Event>>()? {
+                days.entry(day).or_default().extend(events);
+            }
+            Ok(days)
+        }
+    }
+
+    deserializer.deserialize_map(Days)
+}
+
+/// A length of time in the config, written either as a bare number of minutes or as a
+/// duration like `"2m30s"` made of `h`, `m` and `s` parts.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "RawConfigDuration")]
+pub struct ConfigDuration(pub Duration);
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawConfigDuration {
+    Minutes(u32),
+    Text(String),
+}
+
+impl TryFrom<RawConfigDuration> for ConfigDuration {
+    type Error = String;
+
+    fn try_from(value: RawConfigDuration) -> Result<Self, Self::Error> {
+        match value {
+            RawConfigDuration::Minutes(minutes) => bounded(Duration::minutes(minutes as i64))
+                .map(ConfigDuration)
+                .ok_or_else(|| format!("invalid duration {}", minutes)),
+            RawConfigDuration::Text(text) => parse_duration(&text)
+                .map(ConfigDuration)
+                .ok_or_else(|| format!("invalid duration {}", text)),
+        }
+    }
+}
+
+impl FromStr for ConfigDuration {
+    type Err = String;
+
+    /// Parses a number of minutes or a duration like `"90s"`, the same as in the config.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_duration(s)
+            .map(ConfigDuration)
+            .ok_or_else(|| format!("invalid duration {}", s))
+    }
+}
+
+impl Serialize for ConfigDuration {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{}s", self.0.num_seconds()))
+    }
+}
+
+/// How long from an event's start to launch a command, written like a [`ConfigDuration`]
+/// with a leading `-` for before it, as in `-1` or `"-30s"`.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "RawLaunchOffset")]
+pub struct LaunchOffset(pub Duration);
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawLaunchOffset {
+    Minutes(i32),
+    Text(String),
+}
+
+impl TryFrom<RawLaunchOffset> for LaunchOffset {
+    type Error = String;
+
+    fn try_from(value: RawLaunchOffset) -> Result<Self, Self::Error> {
+        match value {
+            RawLaunchOffset::Minutes(minutes) => {
+                bounded(Duration::minutes(minutes.unsigned_abs() as i64))
+                    .map(|_| LaunchOffset(Duration::minutes(minutes as i64)))
+                    .ok_or_else(|| format!("invalid offset {}", minutes))
+            }
+            RawLaunchOffset::Text(text) => text.parse(),
+        }
+    }
+}
+
+impl FromStr for LaunchOffset {
+    type Err = String;
+
+    /// Parses a number of minutes or a duration like `"-90s"`, the same as in the config.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let duration = match s.strip_prefix('-') {
+            Some(rest) => parse_duration(rest).map(|duration| -duration),
+            None => parse_duration(s),
+        };
+        duration
+            .map(LaunchOffset)
+            .ok_or_else(|| format!("invalid offset {}", s))
+    }
+}
+
+impl Serialize for LaunchOffset {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{}s", self.0.num_seconds()))
+    }
+}
+
+/// `duration` if it fits within how far next_class looks ahead. Anything longer couldn't
+/// be waited for anyway, and would overflow the date arithmetic when scheduling.
+fn bounded(duration: Duration) -> Option<Duration> {
+    (duration <= Duration::days(MAX_LOOKAHEAD_DAYS)).then_some(duration)
+}
+
+/// Parse a duration like `"1h"`, `"90s"` or `"2m30s"`. A bare number means minutes.
+fn parse_duration(text: &str) -> Option<Duration> {
+    let text = text.trim();
+    if let Ok(minutes) = text.parse::<u32>() {
+        return bounded(Duration::minutes(minutes as i64));
+    }
+
+    let mut seconds: i64 = 0;
+    let mut rest = text;
+    while !rest.is_empty() {
+        let len = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let amount: i64 = rest[..len].parse().ok()?;
+        let unit = match rest[len..].chars().next()? {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return None,
+        };
+        // checked, so that absurd amounts are rejected rather than overflow.
+        seconds = seconds.checked_add(amount.checked_mul(unit)?)?;
+        rest = &rest[len + 1..];
+    }
+
+    if text.is_empty() || seconds > MAX_LOOKAHEAD_DAYS * 24 * 3600 {
+        return None;
+    }
+    Some(Duration::seconds(seconds))
+}
+
+/// A named timetable that takes the place of the top-level one when selected.
+#[derive(Debug, Deserialize, Clone)]
+pub struct Profile {
+    /// Replaces the top-level `timetable`.
+    #[serde(default, deserialize_with = "merge_days")]
+    pub timetable: HashMap<Day, Vec<Event>>,
+    /// Replaces the top-level `events`.
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub events: HashMap<String, Vec<String>>,
+    /// Added to the top-level commands, replacing those with the same name.
+    #[serde(default)]
+    pub command: HashMap<String, CommandArgs>,
+}
+
+/// An event that happens once, on `date`.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct OnceEvent {
+    /// The date the event happens on.
+    pub date: NaiveDate,
+    /// The event itself, written inline next to `date`.
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Hash, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub enum Day {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl Day {
+    /// Every day of the week, starting on Monday.
+    pub const ALL: [Day; 7] = [
+        Day::Monday,
+        Day::Tuesday,
+        Day::Wednesday,
+        Day::Thursday,
+        Day::Friday,
+        Day::Saturday,
+        Day::Sunday,
+    ];
+
+    /// Every day of the week, starting on `start`.
+    pub fn week_from(start: Day) -> [Day; 7] {
+        let mut days = Day::ALL;
+        days.rotate_left(Day::ALL.iter().position(|day| *day == start).unwrap_or(0));
+        days
+    }
+}
+
+impl fmt::Display for Day {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Day::*;
+
+        let name = match self {
+            Monday => "Monday",
+            Tuesday => "Tuesday",
+            Wednesday => "Wednesday",
+            Thursday => "Thursday",
+            Friday => "Friday",
+            Saturday => "Saturday",
+            Sunday => "Sunday",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl fmt::Display for CommandArgs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut env: Vec<_> = self.env.iter().collect();
+        env.sort();
+        for (key, value) in env {
+            write!(f, "{}={} ", key, value)?;
+        }
+        if self.shell {
+            // show exactly what the shell gets, args are joined without any quoting.
+            return writeln!(f, "sh -c '{}'", self.shell_line());
+        }
+        write!(f, "{} ", self.name)?;
+        for arg in &self.args {
+            write!(f, "{} ", arg)?;
+        }
+        writeln!(f)
+    }
+}
+
+impl Config {
+    /// Parse a config from the contents of a config file, load its time zone and normalize
+    /// it.
+    pub fn from_toml(contents: &str) -> Result<Self, ConfigError> {
+        let mut value: toml::Value = contents.parse().map_err(ConfigError::Parse)?;
+        let version: u32 = match value.get("version") {
+            Some(version) => version.clone().try_into().map_err(ConfigError::Parse)?,
+            None => 0,
+        };
+        if version > CONFIG_VERSION {
+            return Err(ConfigError::UnsupportedVersion(version));
+        }
+
+        let migrated = migrate(&mut value, version);
+        // a config that needed no changes is read from its text, so errors point at a line.
+        let mut config: Config = if migrated.is_empty() {
+            toml::from_str(contents)
+        } else {
+            value.try_into()
+        }
+        .map_err(ConfigError::Parse)?;
+        config.migrated = migrated;
+
+        if let Some(name) = &config.timezone {
+            config.zone =
+                Zone::named(name).map_err(|e| ConfigError::UnknownTimezone(name.clone(), e))?;
+        }
+
+        config.resolve_aliases()?;
+
+        let allow_unset = config.allow_unset_env;
+        let profiles = config
+            .profiles
+            .values_mut()
+            .flat_map(|p| p.command.iter_mut());
+        for (name, command) in config.command.iter_mut().chain(profiles) {
+            command
+                .expand_env(allow_unset)
+                .map_err(|var| ConfigError::UnsetVariable(format!("command.{}", name), var))?;
+        }
+        for (label, event) in config.labelled_events_mut() {
+            let place = inline_place(&label, event);
+            if let Some(command) = &mut event.command {
+                command
+                    .expand_env(allow_unset)
+                    .map_err(|var| ConfigError::UnsetVariable(place, var))?;
+            }
+        }
+
+        config.normalize();
+        Ok(config)
+    }
+
+    /// Resolve the `alias` of every command, in the command tables, their hooks and events'
+    /// own commands. A profile's commands can alias its own as well as top-level ones.
+    fn resolve_aliases(&mut self) -> Result<(), ConfigError> {
+        let top = self.command.clone();
+        for (name, command) in &mut self.command {
+            resolve_alias(command, &top, &format!("command.{}", name))?;
+        }
+        for (day, weekly) in &mut self.timetable {
+            for event in weekly {
+                let place = inline_place(&day.to_string(), event);
+                if let Some(command) = &mut event.command {
+                    resolve_alias(command, &top, &place)?;
+                }
+            }
+        }
+        for once in &mut self.once {
+            let place = inline_place(&once.date.to_string(), &once.event);
+            if let Some(command) = &mut once.event.command {
+                resolve_alias(command, &top, &place)?;
+            }
+        }
+
+        for (name, profile) in &mut self.profiles {
+            let mut commands = top.clone();
+            commands.extend(profile.command.clone());
+            for (command_name, command) in &mut profile.command {
+                let place = format!("profiles.{}.command.{}", name, command_name);
+                resolve_alias(command, &commands, &place)?;
+            }
+            for (day, weekly) in &mut profile.timetable {
+                for event in weekly {
+                    let place = inline_place(&format!("profiles.{} {}", name, day), event);
+                    if let Some(command) = &mut event.command {
+                        resolve_alias(command, &commands, &place)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Sort every day of the timetable and the one-off events by time, dropping events
+    /// written down twice, so the scheduling functions can take the order for granted.
+    /// Events at the same time keep the order they were written in.
+    pub fn normalize(&mut self) {
+        for events in self.timetable.values_mut() {
+            dedup(events);
+            events.sort_by(compare_events);
+        }
+        dedup(&mut self.once);
+        self.once
+            .sort_by(|a, b| a.date.cmp(&b.date).then(compare_events(&a.event, &b.event)));
+    }
+
+    /// Switch to the profile named `name`, or else to `default_profile` if there is one.
+    pub fn select_profile(&mut self, name: Option<&str>) -> Result<(), ConfigError> {
+        let name = match name.or(self.default_profile.as_deref()) {
+            Some(name) => name.to_string(),
+            None => return Ok(()),
+        };
+        let profile = self
+            .profiles
+            .get(&name)
+            .cloned()
+            .ok_or(ConfigError::UnknownProfile(name))?;
+
+        self.timetable = profile.timetable;
+        self.events = profile.events;
+        self.command.extend(profile.command);
+        self.normalize();
+        Ok(())
+    }
+
+    /// The current time in the config's time zone, or `clock` if set.
+    pub fn now(&self) -> DateTime<Zone> {
+        self.clock
+            .unwrap_or_else(|| Utc::now().with_timezone(&self.zone))
+    }
+
+    /// The instant `time` happens on `date` in the config's time zone.
+    pub fn at(&self, date: NaiveDate, time: NaiveTime) -> DateTime<Zone> {
+        let local = date.and_time(time);
+        match self.zone.from_local_datetime(&local).earliest() {
+            Some(at) => at,
+            // the clocks skip over `time` on `date`, read it with the offset from before the
+            // change so it lands just as far past the jump.
+            None => {
+                let before = self
+                    .zone
+                    .offset_from_utc_datetime(&(local - Duration::days(1)))
+                    .fix();
+                self.zone.from_utc_datetime(
+                    &(local - Duration::seconds(before.local_minus_utc() as i64)),
+                )
+            }
+        }
+    }
+
+    /// The weekly events on `day`, sorted by time since the timetable is normalized.
+    pub fn weekly_events(&self, day: Day) -> Vec<&Event> {
+        self.timetable.get(&day).into_iter().flatten().collect()
+    }
+
+    /// Whether there is any event at all, weekly or one-off, even if only disabled ones.
+    pub fn has_events(&self) -> bool {
+        self.timetable.values().any(|events| !events.is_empty()) || !self.once.is_empty()
+    }
+
+    /// When the occurrence of `event` on `date` ends, if it has an `end_time`. Events ending
+    /// before they start run past midnight and end the next day.
+    pub fn end_at(&self, date: NaiveDate, event: &Event) -> Option<DateTime<Zone>> {
+        let end_time = event.end_time?;
+        Some(if end_time < event.time {
+            self.at(date.succ(), end_time)
+        } else {
+            self.at(date, end_time)
+        })
+    }
+
+    /// The first day of the week `date` is in, going by `week_starts_on`.
+    pub fn week_start(&self, date: NaiveDate) -> NaiveDate {
+        let start = Day::ALL.iter().position(|day| *day == self.week_starts_on);
+        let into_week = (date.weekday().num_days_from_monday() as i64 - start.unwrap_or(0) as i64)
+            .rem_euclid(7);
+        date - Duration::days(into_week)
+    }
+
+    /// Which week of the semester `date` is in, the week of `valid_from` being week 1. `None`
+    /// before then or without a `valid_from`.
+    pub fn semester_week(&self, date: NaiveDate) -> Option<u32> {
+        let from = self.week_start(self.valid_from?);
+        let weeks = (self.week_start(date) - from).num_days() / 7;
+        u32::try_from(weeks).ok().map(|weeks| weeks + 1)
+    }
+
+    /// Whether the week of `date` is among the `weeks` of a weekly event, always true for
+    /// one without.
+    pub fn in_weeks(&self, event: &Event, date: NaiveDate) -> bool {
+        event.weeks.is_none_or(|weeks| {
+            self.semester_week(date)
+                .is_some_and(|week| weeks.contains(week))
+        })
+    }
+
+    /// Whether `date` is within `valid_from..=valid_until` and not a holiday.
+    pub fn is_valid_on(&self, date: NaiveDate) -> bool {
+        self.valid_from.is_none_or(|from| from <= date)
+            && self.valid_until.is_none_or(|until| date <= until)
+            && !self.holidays.contains(&date)
+    }
+
+    /// Everything happening on `date`, the enabled weekly events for that day as well as
+    /// one-off events, sorted by time.
+    pub fn events_on(&self, date: NaiveDate) -> Vec<&Event> {
+        if !self.is_valid_on(date) {
+            return Vec::new();
+        }
+
+        let mut events = self.weekly_events(Day::from(date.weekday()));
+        events.retain(|event| event.occurs_in_week_of(date) && self.in_weeks(event, date));
+        let once = self.once.iter().filter(|once| once.date == date);
+        let before = events.len();
+        events.extend(once.map(|once| &once.event));
+        // both are sorted already, only merging them needs another sort.
+        if events.len() != before {
+            events.sort_by(|a, b| compare_events(a, b));
+        }
+        events.retain(|event| event.enabled);
+        events
+    }
+
+    /// The names of the commands an event named `event` runs, falling back to
+    /// `default_command` when it has no entry in `events`.
+    pub fn command_names(&self, event: &str) -> Option<&[String]> {
+        self.event_mapping(event)
+            .map(Vec::as_slice)
+            .or_else(|| self.default_command.as_ref().map(std::slice::from_ref))
+    }
+
+    /// The entry in `events` for an event named `event`. An exact name wins over any glob,
+    /// and among matching globs the longest, so the most specific one, wins; equally long
+    /// ones are tried in alphabetical order.
+    pub fn event_mapping(&self, event: &str) -> Option<&Vec<String>> {
+        if let Some(commands) = self.events.get(event) {
+            return Some(commands);
+        }
+        self.events
+            .iter()
+            .filter(|(pattern, _)| glob_match(pattern, event))
+            .min_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then_with(|| a.cmp(b)))
+            .map(|(_, commands)| commands)
+    }
+
+    /// The commands to run for `event`: its own `command` if it has one, otherwise those
+    /// its name maps to, skipping names that don't exist.
+    pub fn commands_for<'a>(&'a self, event: &'a Event) -> Vec<&'a CommandArgs> {
+        if let Some(command) = &event.command {
+            return vec![command];
+        }
+        self.command_names(&event.event)
+            .into_iter()
+            .flatten()
+            .filter_map(|name| self.command.get(name))
+            .collect()
+    }
+
+    /// Whether an event named `event` is in the timetable or among the one-off events.
+    pub fn is_scheduled(&self, event: &str) -> bool {
+        self.labelled_events()
+            .iter()
+            .any(|(_, scheduled)| scheduled.event == event)
+    }
+
+    /// How long before `event` to notify, taking per-event overrides into account.
+    pub fn notify_before(&self, event: &Event) -> Duration {
+        event.notify_before.unwrap_or(self.notify_before).0
+    }
+
+    /// The `command` of the first scheduled event named `event` that has one.
+    pub fn inline_command(&self, event: &str) -> Option<&CommandArgs> {
+        self.labelled_events()
+            .into_iter()
+            .filter(|(_, scheduled)| scheduled.event == event)
+            .find_map(|(_, scheduled)| scheduled.command.as_ref())
+    }
+
+    /// Whether the daemon launches `event` or only reminds of it, taking per-event overrides
+    /// into account.
+    pub fn auto_launch(&self, event: &Event) -> bool {
+        event.auto_launch.unwrap_or(self.auto_launch)
+    }
+
+    /// Whether the daemon sends a heads-up for `event` and launches it as it starts, taking
+    /// per-event overrides into account.
+    pub fn pre_alert(&self, event: &Event) -> bool {
+        event.pre_alert.unwrap_or(self.pre_alert)
+    }
+
+    /// When the daemon launches `command` for the occurrence of `event` starting at `start`,
+    /// going by the `launch_offset` of the event or else of the command, or else right at the
+    /// start with `pre_alert`. `None` to launch it along with the notification.
+    pub fn launch_at(
+        &self,
+        event: &Event,
+        command: &CommandArgs,
+        start: DateTime<Zone>,
+    ) -> Option<DateTime<Zone>> {
+        event
+            .launch_offset
+            .or(command.launch_offset)
+            .or_else(|| {
+                self.pre_alert(event)
+                    .then_some(LaunchOffset(Duration::zero()))
+            })
+            .map(|offset| start + offset.0)
+    }
+
+    /// Every weekly and one-off event, labelled with its day or date for error messages.
+    fn labelled_events(&self) -> Vec<(String, &Event)> {
+        let mut events = Vec::new();
+        for day in Day::ALL {
+            for event in self.weekly_events(day) {
+                events.push((day.to_string(), event));
+            }
+        }
+        for once in &self.once {
+            events.push((once.date.to_string(), &once.event));
+        }
+        events
+    }
+
+    /// labelled_events, but mutable and in no particular order.
+    fn labelled_events_mut(&mut self) -> Vec<(String, &mut Event)> {
+        let mut events = Vec::new();
+        for (day, weekly) in &mut self.timetable {
+            for event in weekly {
+                events.push((day.to_string(), event));
+            }
+        }
+        for once in &mut self.once {
+            events.push((once.date.to_string(), &mut once.event));
+        }
+        for (name, profile) in &mut self.profiles {
+            for (day, weekly) in &mut profile.timetable {
+                for event in weekly {
+                    events.push((format!("profiles.{} {}", name, day), event));
+                }
+            }
+        }
+        events
+    }
+
+    /// Problems that make the config unusable: an empty validity range, events that end
+    /// when they start and events with no command to run.
+    fn load_problems(&self) -> Vec<Problem> {
+        let mut problems = Vec::new();
+
+        if let (Some(from), Some(until)) = (self.valid_from, self.valid_until) {
+            if until < from {
+                problems.push(Problem::new(
+                    ProblemKind::InvalidRange,
+                    "valid_until",
+                    format!("valid_until {} is before valid_from {}", until, from),
+                ));
+            }
+        }
+
+        for (label, event) in self.labelled_events() {
+            let key = event_key(&label);
+            if event.command.is_none() && self.command_names(&event.event).is_none() {
+                problems.push(Problem::new(
+                    ProblemKind::MissingEventMapping,
+                    format!("events.{}", event.event),
+                    format!(
+                        "{} {}: event {} has no entry in events and there is no default_command",
+                        label, event.time, event.event
+                    ),
+                ));
+            }
+
+            let times = [("time", Some(event.time)), ("end_time", event.end_time)];
+            for (field, time) in times {
+                if let Some(problem) = time.and_then(odd_time) {
+                    problems.push(Problem::new(
+                        ProblemKind::InvalidTime,
+                        &key,
+                        format!(
+                            "{} {}: event {} has {} in its {}",
+                            label, event.time, event.event, problem, field
+                        ),
+                    ));
+                }
+            }
+
+            if let Some(end_time) = event.end_time {
+                if end_time == event.time {
+                    problems.push(Problem::new(
+                        ProblemKind::InvalidTime,
+                        &key,
+                        format!(
+                            "{} {}: event {} ends at {} which is when it starts",
+                            label, event.time, event.event, end_time
+                        ),
+                    ));
+                }
+            }
+
+            let interval = match (event.interval_weeks, event.anchor_date) {
+                (Some(0), _) => Some("an interval_weeks of 0"),
+                (Some(_), None) => Some("interval_weeks but no anchor_date"),
+                _ => None,
+            };
+            let interval = interval.or_else(|| {
+                (event.weeks.is_some() && self.valid_from.is_none())
+                    .then_some("weeks but there is no valid_from to count them from")
+            });
+            if let Some(problem) = interval {
+                problems.push(Problem::new(
+                    ProblemKind::InvalidInterval,
+                    &key,
+                    format!(
+                        "{} {}: event {} has {}",
+                        label, event.time, event.event, problem
+                    ),
+                ));
+            }
+        }
+        problems
+    }
+
+    /// Check invariants that serde can't express, reporting every problem found at once.
+    pub fn validate(&self) -> Result<(), String> {
+        let problems = self.load_problems();
+        let problems: Vec<_> = problems.into_iter().map(|p| p.message).collect();
+        match problems.len() {
+            0 => Ok(()),
+            1 => Err(problems.into_iter().next().unwrap()),
+            n => Err(format!("{} problems:\n  {}", n, problems.join("\n  "))),
+        }
+    }
+
+    /// Every problem with the config, for `validate`: mappings to no or unknown commands and
+    /// events that overlap on the same day, on top of what stops it from loading.
+    pub fn check(&self) -> Vec<Problem> {
+        let mut problems = self.load_problems();
+
+        problems.extend(self.overlaps());
+
+        if let Some(command) = &self.default_command {
+            if !self.command.contains_key(command) {
+                problems.push(Problem::new(
+                    ProblemKind::MissingCommand,
+                    "default_command",
+                    format!("default_command: command {} does not exist", command),
+                ));
+            }
+        }
+
+        let mut mappings: Vec<_> = self.events.iter().collect();
+        mappings.sort();
+        for (event, commands) in mappings {
+            // e.g. left for the user to fill in by import-ical.
+            if commands.is_empty() {
+                problems.push(Problem::new(
+                    ProblemKind::MissingCommand,
+                    format!("events.{}", event),
+                    format!("events.{}: maps to no commands", event),
+                ));
+            }
+            for command in commands {
+                if !self.command.contains_key(command) {
+                    problems.push(Problem::new(
+                        ProblemKind::MissingCommand,
+                        format!("events.{}", event),
+                        format!("events.{}: command {} does not exist", event, command),
+                    ));
+                }
+            }
+        }
+
+        problems
+    }
+
+    /// Commands, or their hooks, whose binary isn't an executable on `PATH` or at the path
+    /// given. Only worth a warning, the binary might come from somewhere we can't see, and
+    /// shell commands or names with placeholders aren't checked at all.
+    pub fn missing_binaries(&self) -> Vec<Problem> {
+        let mut commands: Vec<_> = self
+            .command
+            .iter()
+            .map(|(name, command)| (format!("command.{}", name), command))
+            .collect();
+        commands.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (label, event) in self.labelled_events() {
+            if let Some(command) = &event.command {
+                commands.push((inline_place(&label, event), command));
+            }
+        }
+
+        let mut missing = Vec::new();
+        for (place, command) in commands {
+            let hooks = [
+                ("", Some(command)),
+                (".before", command.before.as_deref()),
+                (".after", command.after.as_deref()),
+            ];
+            for (hook, command) in hooks {
+                let command = match command {
+                    Some(command) if !command.shell && !command.name.contains('{') => command,
+                    _ => continue,
+                };
+                if !binary_exists(&command.name) {
+                    let key = format!("{}{}", place, hook);
+                    let message = format!("{}: {} not found", key, command.name);
+                    missing.push(Problem::new(ProblemKind::MissingBinary, key, message));
+                }
+            }
+        }
+
+        missing
+    }
+
+    /// Weekly events on the same day that start at the same time, or before the one before
+    /// them ends.
+    pub fn overlaps(&self) -> Vec<Problem> {
+        let mut overlaps = Vec::new();
+
+        for day in Day::ALL {
+            let mut events = self.weekly_events(day);
+            // disabled events don't get in anyone's way.
+            events.retain(|event| event.enabled);
+            for (idx, event) in events.iter().enumerate() {
+                for other in &events[idx + 1..] {
+                    // ending before it starts means running until past midnight.
+                    let overlapping = other.time == event.time
+                        || event
+                            .end_time
+                            .is_some_and(|end| end < event.time || other.time < end);
+                    if !overlapping {
+                        break;
+                    }
+                    // events in odd and in even weeks never meet.
+                    if event
+                        .week_parity
+                        .zip(other.week_parity)
+                        .is_some_and(|(a, b)| a != b)
+                    {
+                        continue;
+                    }
+                    overlaps.push(Problem::new(
+                        ProblemKind::Overlap,
+                        event_key(&day.to_string()),
+                        format!(
+                            "{} {}: event {} overlaps with {} at {}",
+                            day, event.time, event.event, other.event, other.time
+                        ),
+                    ));
+                }
+            }
+        }
+
+        overlaps
+    }
+}
+
+impl CommandArgs {
+    /// Expand `$VAR` and `${VAR}` in everything but the names of `env`, and in the hooks.
+    /// `name` and `args` of shell commands are left to the shell, and a leading `~` in `cwd`,
+    /// `log_output` and `icon` becomes `$HOME`. Fails with the name of the first unset
+    /// variable, unless `allow_unset`.
+    fn expand_env(&mut self, allow_unset: bool) -> Result<(), String> {
+        let expand = |text: &mut String| -> Result<(), String> {
+            *text = expand_env(text, allow_unset)?;
+            Ok(())
+        };
+
+        if !self.shell {
+            expand(&mut self.name)?;
+            self.args.iter_mut().try_for_each(expand)?;
+        }
+        self.env.values_mut().try_for_each(expand)?;
+        for path in [&mut self.cwd, &mut self.log_output, &mut self.icon]
+            .into_iter()
+            .flatten()
+        {
+            *path = expand_home(&expand_env(path, allow_unset)?);
+        }
+        for hook in [&mut self.before, &mut self.after].into_iter().flatten() {
+            hook.expand_env(allow_unset)?;
+        }
+        Ok(())
+    }
+
+    /// A copy with `{event}`, `{date}`, `{time}` and `{weekday}` in `name` and `args`, and
+    /// in those of its hooks, filled in for the occurrence of `event` starting at `start`.
+    /// Unknown placeholders are left alone, as is `{event}` without an event.
+    pub fn expand(&self, event: Option<&str>, start: DateTime<Zone>) -> CommandArgs {
+        let mut vars = vec![
+            ("{date}", start.format("%Y-%m-%d").to_string()),
+            ("{time}", start.format("%H:%M").to_string()),
+            ("{weekday}", Day::from(start.weekday()).to_string()),
+        ];
+        // last, so placeholders in the event's name are kept as they are.
+        if let Some(event) = event {
+            vars.push(("{event}", event.to_string()));
+        }
+        self.substitute(&vars)
+    }
+
+    fn substitute(&self, vars: &[(&str, String)]) -> CommandArgs {
+        let fill = |text: &str| {
+            vars.iter().fold(text.to_string(), |text, (key, value)| {
+                text.replace(key, value)
+            })
+        };
+
+        CommandArgs {
+            name: fill(&self.name),
+            args: self.args.iter().map(|arg| fill(arg)).collect(),
+            before: self
+                .before
+                .as_ref()
+                .map(|before| Box::new(before.substitute(vars))),
+            after: self
+                .after
+                .as_ref()
+                .map(|after| Box::new(after.substitute(vars))),
+            ..self.clone()
+        }
+    }
+
+    /// `name` and `args` joined into one line for `sh -c`.
+    fn shell_line(&self) -> String {
+        let mut line = self.name.clone();
+        for arg in &self.args {
+            line.push(' ');
+            line.push_str(arg);
+        }
+        line
+    }
+
+    /// Whether some process is running the same binary, going by the file name of the first
+    /// word of `name` and of each process's `argv[0]` in `/proc`. Always false without one.
+    pub fn is_running(&self) -> bool {
+        let binary = match self.binary_name() {
+            Some(binary) => binary,
+            None => return false,
+        };
+
+        let processes = match fs::read_dir("/proc") {
+            Ok(processes) => processes,
+            Err(_) => return false,
+        };
+        processes.flatten().any(|process| {
+            // only the numbered directories are processes.
+            if !process
+                .file_name()
+                .to_string_lossy()
+                .bytes()
+                .all(|b| b.is_ascii_digit())
+            {
+                return false;
+            }
+            let cmdline = fs::read(process.path().join("cmdline")).unwrap_or_default();
+            let argv0 = cmdline.split(|&b| b == 0).next().unwrap_or_default();
+            Path::new(&*String::from_utf8_lossy(argv0)).file_name() == Some(binary)
+        })
+    }
+
+    /// Bring a window of the binary to the front, matching its window class with `wmctrl`.
+    /// Whether there was one to, false without `wmctrl` too.
+    pub fn focus_window(&self) -> bool {
+        let binary = match self.binary_name() {
+            Some(binary) => binary,
+            None => return false,
+        };
+        Command::new("wmctrl")
+            .arg("-x")
+            .arg("-a")
+            .arg(binary)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+    }
+
+    /// The file name of the first word of `name`.
+    fn binary_name(&self) -> Option<&OsStr> {
+        let binary = self.name.split_whitespace().next().unwrap_or_default();
+        Path::new(binary).file_name()
+    }
+
+    /// Spawn the command without waiting for it to finish.
+    pub fn spawn(&self) -> io::Result<()> {
+        self.log_header()?;
+        self.respawn()
+    }
+
+    /// spawn, but without marking another run in `log_output`, for trying again after
+    /// spawning failed.
+    pub fn respawn(&self) -> io::Result<()> {
+        let mut child = self.command()?.spawn()?;
+        // reap the child in the background so it doesn't linger as a zombie.
+        thread::spawn(move || child.wait());
+        Ok(())
+    }
+
+    /// Run the command to completion and return how it exited.
+    pub fn status(&self) -> io::Result<ExitStatus> {
+        self.log_header()?;
+        self.command()?.status()
+    }
+
+    /// Run the command to completion, failing if it doesn't exit successfully.
+    pub fn run(&self) -> io::Result<()> {
+        let status = self.status()?;
+        if !status.success() {
+            return Err(io::Error::other(format!(
+                "{} exited with {}",
+                self.name, status
+            )));
+        }
+        Ok(())
+    }
+
+    /// Mark the start of a run in `log_output`, if set, with the time and the command line.
+    pub fn log_header(&self) -> io::Result<()> {
+        if let Some(path) = &self.log_output {
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            writeln!(
+                file,
+                "==> {} {}",
+                Local::now().to_rfc3339_opts(SecondsFormat::Secs, false),
+                self.to_string().trim_end()
+            )?;
+        }
+        Ok(())
+    }
+
+    /// The process to launch, with its environment and working directory set up.
+    fn command(&self) -> io::Result<Command> {
+        let mut command = if self.shell {
+            let mut command = Command::new("sh");
+            command.arg("-c").arg(self.shell_line());
+            command
+        } else {
+            let mut command = Command::new(&self.name);
+            command.args(&self.args);
+            command
+        };
+        command.envs(&self.env);
+
+        if self.detach {
+            // setsid is async-signal-safe, so fine to call between fork and exec.
+            unsafe {
+                command.pre_exec(|| match libc::setsid() {
+                    -1 => Err(io::Error::last_os_error()),
+                    _ => Ok(()),
+                });
+            }
+        }
+
+        if let Some(cwd) = &self.cwd {
+            if !Path::new(cwd).is_dir() {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("working directory {} does not exist", cwd),
+                ));
+            }
+            command.current_dir(cwd);
+        }
+
+        if let Some(path) = &self.log_output {
+            let file = OpenOptions::new().create(true).append(true).open(path)?;
+            command.stdout(file.try_clone()?).stderr(file);
+        }
+
+        Ok(command)
+    }
+}
+
+impl TryFrom<String> for Day {
+    type Error = String;
+
+    /// Accepts three-letter codes, full weekday names and ISO weekday numbers (1 = Monday),
+    /// ignoring case and surrounding whitespace.
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        use Day::*;
+
+        match value.trim().to_lowercase().as_str() {
+            "mon" | "monday" | "1" => Ok(Monday),
+            "tue" | "tuesday" | "2" => Ok(Tuesday),
+            "teu" => {
+                static LEGACY_TEU: Once = Once::new();
+                LEGACY_TEU
+                    .call_once(|| log::warn!("day key \"teu\" is deprecated, use \"tue\" instead"));
+                Ok(Tuesday)
+            }
+            "wed" | "wednesday" | "3" => Ok(Wednesday),
+            "thu" | "thursday" | "4" => Ok(Thursday),
+            "fri" | "friday" | "5" => Ok(Friday),
+            "sat" | "saturday" | "6" => Ok(Saturday),
+            "sun" | "sunday" | "7" => Ok(Sunday),
+            _ => Err(format!("invalid day {}", value)),
+        }
+    }
+}
+
+impl From<chrono::Weekday> for Day {
+    fn from(day: chrono::Weekday) -> Self {
+        use chrono::Weekday::*;
+
+        match day {
+            Mon => Day::Monday,
+            Tue => Day::Tuesday,
+            Wed => Day::Wednesday,
+            Thu => Day::Thursday,
+            Fri => Day::Friday,
+            Sat => Day::Saturday,
+            Sun => Day::Sunday,
+        }
+    }
+}
+
+/// Expand `$VAR` and `${VAR}` in `text` from the environment, with `$$` for a literal `$`.
+/// An unset variable is an error naming it, or expands to nothing with a warning when
+/// `allow_unset`.
+fn expand_env(text: &str, allow_unset: bool) -> Result<String, String> {
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(idx) = rest.find('$') {
+        out.push_str(&rest[..idx]);
+        rest = &rest[idx + 1..];
+
+        if let Some(after) = rest.strip_prefix('$') {
+            out.push('$');
+            rest = after;
+            continue;
+        }
+
+        let (name, len) = match rest.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(end) => (&braced[..end], end + 2),
+                None => ("", 0),
+            },
+            None => {
+                let end = rest
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len());
+                (&rest[..end], end)
+            }
+        };
+
+        // a lone `$` isn't a variable.
+        if name.is_empty() {
+            out.push('$');
+            continue;
+        }
+        match env::var(name) {
+            Ok(value) => out.push_str(&value),
+            Err(_) if allow_unset => {
+                log::warn!("${} is not set, expanding it to nothing", name)
+            }
+            Err(_) => return Err(name.to_string()),
+        }
+        rest = &rest[len..];
+    }
+    out.push_str(rest);
+
+    Ok(out)
+}
+
+/// Expand a leading `~` in `path` to `$HOME`.
+fn expand_home(path: &str) -> String {
+    match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => match env::var("HOME") {
+            Ok(home) => format!("{}{}", home, rest),
+            Err(_) => path.to_string(),
+        },
+        _ => path.to_string(),
+    }
+}
+
+pub fn compare_events(a: &Event, b: &Event) -> std::cmp::Ordering {
+    a.time.cmp(&b.time)
+}
+
+/// get event and commands for today, and whether the event already started.
+pub fn get_event_and_command(config: &Config) -> Option<(Event, Vec<&CommandArgs>, bool)> {
+    get_event_and_command_at(config, config.now())
+}
+
+/// get_event_and_command as if it were `now`.
+pub fn get_event_and_command_at(
+    config: &Config,
+    now: DateTime<Zone>,
+) -> Option<(Event, Vec<&CommandArgs>, bool)> {
+    let time_now = now.time();
+    let today = now.date().naive_local();
+
+    let events = config.events_on(today);
+
+    // the latest event that already started is still live within the grace window. Just
+    // after midnight that may be the last one of yesterday.
+    let grace = config.grace_after.0;
+    let yesterday = today.pred();
+    let started = match events[..events.partition_point(|e| e.time <= time_now)].last() {
+        Some(event) => Some((today, *event)),
+        None => config
+            .events_on(yesterday)
+            .last()
+            .map(|event| (yesterday, *event)),
+    };
+    if let Some((date, event)) = started {
+        if now - config.at(date, event.time) < grace {
+            return Some((event.clone(), config.commands_for(event), true));
+        }
+    }
+
+    // the first event at or after now, `None` when now is past the last one. Unlike
+    // binary_search this always lands on the first of several events at the same time.
+    let event = events.get(events.partition_point(|e| e.time < time_now))?;
+
+    if config.at(today, event.time) - now > config.notify_before(event) {
+        Some(((*event).clone(), config.commands_for(event), false))
+    } else {
+        None
+    }
+}
+
+/// The latest event that started by `now` and is still on, along with when it started.
+/// Without an `end_time` an event is on for `grace_after`.
+pub fn live_event_at(config: &Config, now: DateTime<Zone>) -> Option<(DateTime<Zone>, &Event)> {
+    let today = now.date().naive_local();
+    let grace = config.grace_after.0;
+
+    config.events_on(today).into_iter().rev().find_map(|event| {
+        let start = config.at(today, event.time);
+        let on = match config.end_at(today, event) {
+            Some(end) => now < end,
+            None => now - start < grace,
+        };
+        (start <= now && on).then_some((start, event))
+    })
+}
+
+/// How many days ahead next_class looks for an event before giving up.
+pub const MAX_LOOKAHEAD_DAYS: i64 = 366;
+
+/// get duration to sleep till next class, as well as commands and event.
+pub fn next_class(config: &Config) -> Option<(StdDuration, Vec<&CommandArgs>, Event)> {
+    next_class_at(config, config.now())
+}
+
+/// next_class as if it were `now`.
+pub fn next_class_at(
+    config: &Config,
+    now: DateTime<Zone>,
+) -> Option<(StdDuration, Vec<&CommandArgs>, Event)> {
+    let today = now.date().naive_local();
+
+    // how far ahead of its start any event is notified for, so we know when a later day
+    // can't have an earlier notification anymore.
+    let longest = config
+        .timetable
+        .values()
+        .flatten()
+        .chain(config.once.iter().map(|once| &once.event))
+        .map(|event| config.notify_before(event))
+        .max()
+        .unwrap_or_else(Duration::zero);
+
+    // with per-event offsets the soonest event to notify for isn't necessarily the soonest
+    // to start, nor even on the same day: an early event tomorrow can be notified for before
+    // a late one today. So compare in real time, which also keeps a DST change in between
+    // from throwing us off by an hour.
+    let mut next: Option<(DateTime<Zone>, &Event)> = None;
+    // keep looking past the coming week, holidays can push the next event further out.
+    for diff in 0..=MAX_LOOKAHEAD_DAYS {
+        let date = today + Duration::days(diff);
+        let earliest = config.at(date, NaiveTime::from_hms(0, 0, 0)) - longest;
+        if next.is_some_and(|(notify_at, _)| notify_at <= earliest) {
+            break;
+        }
+
+        // only events at or after now are candidates.
+        for event in config.events_on(date) {
+            let start = config.at(date, event.time);
+            let notify_at = start - config.notify_before(event);
+            if start >= now && next.is_none_or(|(best, _)| notify_at < best) {
+                next = Some((notify_at, event));
+            }
+        }
+    }
+    // inside the notification window this bottoms out at 0.
+    let (notify_at, event) = next?;
+
+    Some((
+        (notify_at - now).to_std().unwrap_or_default(),
+        config.commands_for(event),
+        event.clone(),
+    ))
+}
+
+/// Every event starting at or after `now`, soonest first, along with when it starts. Looks
+/// as far ahead as next_class does.
+pub fn upcoming_at(
+    config: &Config,
+    now: DateTime<Zone>,
+) -> impl Iterator<Item = (DateTime<Zone>, &Event)> + '_ {
+    let today = now.date().naive_local();
+
+    (0..=MAX_LOOKAHEAD_DAYS)
+        .flat_map(move |diff| {
+            let date = today + Duration::days(diff);
+            config
+                .events_on(date)
+                .into_iter()
+                .map(move |event| (config.at(date, event.time), event))
+        })
+        .filter(move |(start, _)| *start >= now)
+}
+
+/// Why a config couldn't be loaded.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The config isn't valid TOML or doesn't have the expected shape.
+    Parse(toml::de::Error),
+    /// The `timezone` couldn't be loaded from the zoneinfo database.
+    UnknownTimezone(String, io::Error),
+    /// There is no profile with this name.
+    UnknownProfile(String),
+    /// The config's `version` is newer than `CONFIG_VERSION`.
+    UnsupportedVersion(u32),
+    /// The command at this place in the config uses an environment variable that isn't set.
+    UnsetVariable(String, String),
+    /// The command at this place in the config aliases one that doesn't exist.
+    UnknownAlias(String, String),
+    /// The command at this place in the config follows aliases that lead back to themselves.
+    AliasCycle(String, Vec<String>),
+    /// The command at this place in the config has neither a `name` nor an `alias`.
+    MissingName(String),
+    /// The command at this place in the config has both a `name` and an `alias`.
+    NameAndAlias(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Parse(e) => e.fmt(f),
+            ConfigError::UnknownTimezone(name, e) => write!(f, "unknown timezone {}: {}", name, e),
+            ConfigError::UnknownProfile(name) => write!(f, "unknown profile {}", name),
+            ConfigError::UnsupportedVersion(version) => write!(
+                f,
+                "config version {} is newer than the {} this eventjoiner reads",
+                version, CONFIG_VERSION
+            ),
+            ConfigError::UnsetVariable(place, var) => write!(f, "{}: ${} is not set", place, var),
+            ConfigError::UnknownAlias(place, target) => {
+                write!(f, "{}: alias {} does not exist", place, target)
+            }
+            ConfigError::AliasCycle(place, chain) => {
+                write!(f, "{}: aliases form a cycle: {}", place, chain.join(" -> "))
+            }
+            ConfigError::MissingName(place) => {
+                write!(f, "{}: command has neither a name nor an alias", place)
+            }
+            ConfigError::NameAndAlias(place) => {
+                write!(f, "{}: command has both a name and an alias", place)
+            }
+        }
+    }
+}
+
+impl error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ConfigError::Parse(e) => Some(e),
+            ConfigError::UnknownTimezone(_, e) => Some(e),
+            ConfigError::UnknownProfile(_)
+            | ConfigError::UnsupportedVersion(_)
+            | ConfigError::UnsetVariable(..)
+            | ConfigError::UnknownAlias(..)
+            | ConfigError::AliasCycle(..)
+            | ConfigError::MissingName(_)
+            | ConfigError::NameAndAlias(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A config made of `toml`, which sets at least `notify_before`, with every event running
+    /// a command that does nothing.
+    fn config(toml: &str) -> Config {
+        let toml = format!(
+            "version = {}\ndefault_command = 'nothing'\n{}\n[command.nothing]\nname = 'true'\n",
+            CONFIG_VERSION, toml
+        );
+        Config::from_toml(&toml).unwrap()
+    }
+
+    /// `time` on `date`, written like "2024-01-03" and "09:55", in the config's zone.
+    fn at(config: &Config, date: &str, time: &str) -> DateTime<Zone> {
+        let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap();
+        config.at(date, NaiveTime::parse_from_str(time, "%H:%M").unwrap())
+    }
+
+    fn minutes(minutes: u64) -> StdDuration {
+        StdDuration::from_secs(minutes * 60)
+    }
+
+    #[test]
+    fn day_accepts_codes_names_and_numbers() {
+        let spellings = [
+            (Day::Monday, ["mon", "monday", "Monday", "MON", "1"]),
+            (Day::Tuesday, ["tue", "tuesday", "Tuesday", "TUE", "2"]),
+            (
+                Day::Wednesday,
+                ["wed", "wednesday", "Wednesday", "WED", "3"],
+            ),
+            (Day::Thursday, ["thu", "thursday", "Thursday", "THU", "4"]),
+            (Day::Friday, ["fri", "friday", "Friday", "FRI", "5"]),
+            (Day::Saturday, ["sat", "saturday", "Saturday", "SAT", "6"]),
+            (Day::Sunday, ["sun", "sunday", "Sunday", "SUN", "7"]),
+        ];
+        for (day, spellings) in spellings {
+            for spelling in spellings {
+                assert_eq!(Day::try_from(spelling.to_string()), Ok(day), "{}", spelling);
+            }
+        }
+        assert_eq!(Day::try_from(" Friday\n".to_string()), Ok(Day::Friday));
+    }
+
+    #[test]
+    fn day_error_names_the_input() {
+        for input in ["funday", "0", "8", "mo", ""] {
+            assert_eq!(
+                Day::try_from(input.to_string()),
+                Err(format!("invalid day {}", input))
+            );
+        }
+    }
+
+    #[test]
+    fn notify_before_override_is_notified_first() {
+        // 2024-01-03 is a Wednesday.
+        let config = config(
+            "notify_before = 5
+            [timetable]
+            wed = [
+                { time = '09:50:00', event = 'lecture' },
+                { time = '10:00:00', event = 'lab', notify_before = 30 },
+            ]",
+        );
+        // the lab's notification at 09:30 comes before the lecture's at 09:45.
+        let now = at(&config, "2024-01-03", "09:00");
+        let (duration, _, event) = next_class_at(&config, now).unwrap();
+        assert_eq!(event.event, "lab");
+        assert_eq!(duration, minutes(30));
+        assert_eq!(config.notify_before(&event), Duration::minutes(30));
+    }
+
+    #[test]
+    fn notify_before_override_widens_the_window() {
+        let default = config(
+            "notify_before = 5
+            [timetable]
+            wed = [{ time = '10:00:00', event = 'lab' }]",
+        );
+        let early = config(
+            "notify_before = 5
+            [timetable]
+            wed = [{ time = '10:00:00', event = 'lab', notify_before = 30 }]",
+        );
+
+        // 25 minutes ahead is inside a 30 minute window but not a 5 minute one.
+        let now = at(&default, "2024-01-03", "09:35");
+        assert!(get_event_and_command_at(&default, now).is_some());
+        assert!(get_event_and_command_at(&early, now).is_none());
+    }
+
+    #[test]
+    fn empty_day_is_skipped() {
+        // 2024-01-01 is a Monday.
+        let config = config(
+            "notify_before = 5
+            [timetable]
+            mon = []
+            tue = [{ time = '09:00:00', event = 'lecture' }]",
+        );
+        let now = at(&config, "2024-01-01", "08:00");
+        assert!(get_event_and_command_at(&config, now).is_none());
+
+        let (duration, _, event) = next_class_at(&config, now).unwrap();
+        assert_eq!(event.event, "lecture");
+        assert_eq!(duration, minutes(24 * 60 + 55));
+    }
+
+    #[test]
+    fn after_the_last_event_of_the_day() {
+        // 2024-01-03 is a Wednesday.
+        let config = config(
+            "notify_before = 5
+            [timetable]
+            wed = [
+                { time = '09:00:00', event = 'lecture' },
+                { time = '11:00:00', event = 'lab' },
+            ]",
+        );
+        let now = at(&config, "2024-01-03", "18:00");
+        assert!(get_event_and_command_at(&config, now).is_none());
+
+        // nothing is left today, so it's next week's lecture.
+        let (duration, _, event) = next_class_at(&config, now).unwrap();
+        assert_eq!(event.event, "lecture");
+        let notify_at = at(&config, "2024-01-10", "08:55");
+        assert_eq!(duration, (notify_at - now).to_std().unwrap());
+    }
+
+    #[test]
+    fn exactly_at_an_event() {
+        let config = config(
+            "notify_before = 5
+            grace_after = 10
+            [timetable]
+            wed = [
+                { time = '09:00:00', event = 'lecture' },
+                { time = '11:00:00', event = 'lab' },
+            ]",
+        );
+        let now = at(&config, "2024-01-03", "09:00");
+        let (event, _, started) = get_event_and_command_at(&config, now).unwrap();
+        assert_eq!(event.event, "lecture");
+        assert!(started);
+
+        // an event starting right now is still to come, and already due.
+        let (duration, _, event) = next_class_at(&config, now).unwrap();
+        assert_eq!(event.event, "lecture");
+        assert!(duration.is_zero());
+
+        // the same goes for the last event of the day.
+        let now = at(&config, "2024-01-03", "11:00");
+        let (event, _, started) = get_event_and_command_at(&config, now).unwrap();
+        assert_eq!(event.event, "lab");
+        assert!(started);
+        let (duration, _, event) = next_class_at(&config, now).unwrap();
+        assert_eq!(event.event, "lab");
+        assert!(duration.is_zero());
+    }
+
+    #[test]
+    fn valid_only_within_the_date_range() {
+        let backwards = config(
+            "notify_before = 5
+            valid_from = '2024-01-19'
+            valid_until = '2024-01-08'
+            [timetable]",
+        );
+        assert_eq!(
+            backwards.validate(),
+            Err("valid_until 2024-01-08 is before valid_from 2024-01-19".to_string())
+        );
+
+        let config = config(
+            "notify_before = 5
+            valid_from = '2024-01-08'
+            valid_until = '2024-01-19'
+            [timetable]
+            wed = [{ time = '09:00:00', event = 'lecture' }]",
+        );
+        let date = |date: &str| NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap();
+        assert!(!config.is_valid_on(date("2024-01-07")));
+        assert!(config.is_valid_on(date("2024-01-08")));
+        assert!(config.is_valid_on(date("2024-01-12")));
+        assert!(config.is_valid_on(date("2024-01-19")));
+        assert!(!config.is_valid_on(date("2024-01-20")));
+
+        // before the range, the first event is the first one inside it.
+        let now = at(&config, "2024-01-03", "08:00");
+        assert!(get_event_and_command_at(&config, now).is_none());
+        let (duration, _, _) = next_class_at(&config, now).unwrap();
+        let notify_at = at(&config, "2024-01-10", "08:55");
+        assert_eq!(duration, (notify_at - now).to_std().unwrap());
+
+        let now = at(&config, "2024-01-10", "08:00");
+        assert!(get_event_and_command_at(&config, now).is_some());
+
+        let now = at(&config, "2024-01-24", "08:00");
+        assert!(get_event_and_command_at(&config, now).is_none());
+        assert!(next_class_at(&config, now).is_none());
+    }
+
+    #[test]
+    fn holiday_pushes_to_the_next_day() {
+        // 2024-01-08 is a Monday.
+        let long_break = config(
+            "notify_before = 5
+            holidays = ['2024-01-08', '2024-01-09', '2024-01-15', '2024-01-16']
+            [timetable]
+            mon = [{ time = '09:00:00', event = 'monday lecture' }]
+            tue = [{ time = '09:00:00', event = 'tuesday lecture' }]",
+        );
+        let config = config(
+            "notify_before = 5
+            holidays = ['2024-01-08']
+            [timetable]
+            mon = [{ time = '09:00:00', event = 'monday lecture' }]
+            tue = [{ time = '09:00:00', event = 'tuesday lecture' }]",
+        );
+        let now = at(&config, "2024-01-07", "12:00");
+        let (duration, _, event) = next_class_at(&config, now).unwrap();
+        assert_eq!(event.event, "tuesday lecture");
+        let notify_at = at(&config, "2024-01-09", "08:55");
+        assert_eq!(duration, (notify_at - now).to_std().unwrap());
+
+        let now = at(&config, "2024-01-08", "08:00");
+        assert!(get_event_and_command_at(&config, now).is_none());
+
+        // a run of holidays pushes it past the coming week.
+        let now = at(&long_break, "2024-01-07", "12:00");
+        let (duration, _, event) = next_class_at(&long_break, now).unwrap();
+        assert_eq!(event.event, "monday lecture");
+        let notify_at = at(&long_break, "2024-01-22", "08:55");
+        assert_eq!(duration, (notify_at - now).to_std().unwrap());
+    }
+
+    #[test]
+    fn scheduling_with_an_injected_now() {
+        // 2024-01-03 is a Wednesday.
+        let config = config(
+            "notify_before = 5
+            [timetable]
+            wed = [
+                { time = '09:00:00', event = 'lecture' },
+                { time = '10:30:00', event = 'lab' },
+                { time = '14:00:00', event = 'seminar' },
+            ]",
+        );
+        let now = at(&config, "2024-01-03", "09:55");
+
+        let (duration, commands, event) = next_class_at(&config, now).unwrap();
+        assert_eq!(event.event, "lab");
+        assert_eq!(duration, minutes(30));
+        assert_eq!(commands.len(), 1);
+        assert_eq!(commands[0].name, "true");
+
+        let (event, _, started) = get_event_and_command_at(&config, now).unwrap();
+        assert_eq!(event.event, "lab");
+        assert!(!started);
+
+        // the clock is only read when no time is given.
+        let mut config = config;
+        config.clock = Some(now);
+        assert_eq!(next_class(&config).unwrap().0, minutes(30));
+        assert_eq!(get_event_and_command(&config).unwrap().0.event, "lab");
+    }
+
+    #[test]
+    fn odd_weeks_only_across_a_month() {
+        let config = config(
+            "notify_before = 5
+            [timetable]
+            wed = [
+                { time = '09:00:00', event = 'lecture' },
+                { time = '14:00:00', event = 'lab', week_parity = 'odd' },
+            ]",
+        );
+        // the Wednesdays of January 2024 are in ISO weeks 1 to 5.
+        let wednesdays = [
+            ("2024-01-03", true),
+            ("2024-01-10", false),
+            ("2024-01-17", true),
+            ("2024-01-24", false),
+            ("2024-01-31", true),
+        ];
+        for (date, lab) in wednesdays {
+            let events = config.events_on(NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap());
+            let names: Vec<_> = events.iter().map(|event| event.event.as_str()).collect();
+            let expected = if lab {
+                vec!["lecture", "lab"]
+            } else {
+                vec!["lecture"]
+            };
+            assert_eq!(names, expected, "{}", date);
+        }
+
+        // from an even week, the next lab is in the week after.
+        let now = at(&config, "2024-01-10", "12:00");
+        let (start, _) = upcoming_at(&config, now)
+            .find(|(_, event)| event.event == "lab")
+            .unwrap();
+        assert_eq!(start, at(&config, "2024-01-17", "14:00"));
+        let now = at(&config, "2024-01-17", "12:00");
+        let (duration, _, event) = next_class_at(&config, now).unwrap();
+        assert_eq!(event.event, "lab");
+        assert_eq!(duration, minutes(115));
+    }
+
+    #[test]
+    fn notification_already_past_clamps_to_zero() {
+        // notifying two hours ahead, an hour before the event is well inside the window, so
+        // the time until the notification comes out negative.
+        let config = config(
+            "notify_before = 120
+            [timetable]
+            wed = [{ time = '10:00:00', event = 'lecture' }]",
+        );
+        let now = at(&config, "2024-01-03", "09:00");
+        let (duration, _, event) = next_class_at(&config, now).unwrap();
+        assert_eq!(event.event, "lecture");
+        assert!(duration.is_zero());
+        assert!(get_event_and_command_at(&config, now).is_none());
+    }
+
+    #[test]
+    fn scheduling_around_midnight() {
+        // 2024-01-07 is a Sunday.
+        let config = config(
+            "notify_before = 30
+            grace_after = 10
+            [timetable]
+            sun = [{ time = '23:55:00', event = 'late' }]
+            mon = [{ time = '00:10:00', event = 'early' }]",
+        );
+
+        // the notification for a Monday event rolls back into Sunday.
+        let now = at(&config, "2024-01-07", "23:00");
+        let (duration, _, event) = next_class_at(&config, now).unwrap();
+        assert_eq!(event.event, "late");
+        assert_eq!(duration, minutes(25));
+        let now = at(&config, "2024-01-07", "23:56");
+        let (duration, _, event) = next_class_at(&config, now).unwrap();
+        assert_eq!(event.event, "early");
+        assert!(duration.is_zero());
+
+        // just after midnight, Sunday's event is still within its grace period.
+        let now = at(&config, "2024-01-08", "00:02");
+        let (event, _, started) = get_event_and_command_at(&config, now).unwrap();
+        assert_eq!(event.event, "late");
+        assert!(started);
+        // once that's over, Monday's is already inside its window.
+        let now = at(&config, "2024-01-08", "00:06");
+        assert!(get_event_and_command_at(&config, now).is_none());
+        let (duration, _, event) = next_class_at(&config, now).unwrap();
+        assert_eq!(event.event, "early");
+        assert!(duration.is_zero());
+    }
+
+    #[test]
+    fn disabled_event_is_never_next() {
+        let only_disabled = config(
+            "notify_before = 5
+            [timetable]
+            wed = [{ time = '09:00:00', event = 'muted', enabled = false }]",
+        );
+        let config = config(
+            "notify_before = 5
+            [timetable]
+            wed = [
+                { time = '09:00:00', event = 'muted', enabled = false },
+                { time = '11:00:00', event = 'lecture' },
+            ]",
+        );
+        let now = at(&config, "2024-01-03", "08:00");
+        let (_, _, event) = next_class_at(&config, now).unwrap();
+        assert_eq!(event.event, "lecture");
+        assert!(upcoming_at(&config, now)
+            .take(20)
+            .all(|(_, event)| event.event == "lecture"));
+        assert!(next_class_at(&only_disabled, now).is_none());
+    }
+
+    #[test]
+    fn unsorted_timetable_is_normalized() {
+        let config = config(
+            "notify_before = 5
+            [timetable]
+            wed = [
+                { time = '14:00:00', event = 'seminar' },
+                { time = '09:00:00', event = 'lecture' },
+                { time = '11:00:00', event = 'lab' },
+                { time = '09:00:00', event = 'lecture' },
+            ]",
+        );
+        let names: Vec<_> = config
+            .weekly_events(Day::Wednesday)
+            .iter()
+            .map(|event| event.event.as_str())
+            .collect();
+        assert_eq!(names, ["lecture", "lab", "seminar"]);
+
+        let now = at(&config, "2024-01-03", "08:00");
+        let (duration, _, event) = next_class_at(&config, now).unwrap();
+        assert_eq!(event.event, "lecture");
+        assert_eq!(duration, minutes(55));
+
+        let now = at(&config, "2024-01-03", "09:30");
+        let (event, _, _) = get_event_and_command_at(&config, now).unwrap();
+        assert_eq!(event.event, "lab");
+        let now = at(&config, "2024-01-03", "12:00");
+        let (event, _, _) = get_event_and_command_at(&config, now).unwrap();
+        assert_eq!(event.event, "seminar");
+    }
+
+    #[test]
+    fn empty_timetable() {
+        let config = config("notify_before = 5\n[timetable]");
+        assert!(!config.has_events());
+
+        let now = at(&config, "2024-01-03", "09:00");
+        assert!(next_class_at(&config, now).is_none());
+        assert!(get_event_and_command_at(&config, now).is_none());
+        assert!(live_event_at(&config, now).is_none());
+        assert!(upcoming_at(&config, now).next().is_none());
+    }
+
+    #[test]
+    fn week_start_follows_week_starts_on() {
+        let date = |date: &str| NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap();
+        let monday = config("notify_before = 5\nweek_starts_on = 'monday'");
+        let sunday = config("notify_before = 5\nweek_starts_on = 'sunday'");
+
+        // 2024-01-03 is a Wednesday.
+        assert_eq!(monday.week_start(date("2024-01-03")), date("2024-01-01"));
+        assert_eq!(sunday.week_start(date("2024-01-03")), date("2023-12-31"));
+
+        // a Sunday ends a Monday week and starts a Sunday one.
+        assert_eq!(monday.week_start(date("2024-01-07")), date("2024-01-01"));
+        assert_eq!(sunday.week_start(date("2024-01-07")), date("2024-01-07"));
+
+        // and the other way around for a Monday.
+        assert_eq!(monday.week_start(date("2024-01-08")), date("2024-01-08"));
+        assert_eq!(sunday.week_start(date("2024-01-08")), date("2024-01-07"));
+    }
+
+    #[test]
+    fn launch_offset_zero_launches_at_the_start() {
+        let config = config(
+            "notify_before = 10
+            [timetable]
+            wed = [
+                { time = '10:00:00', event = 'lecture', launch_offset = 0 },
+                { time = '11:00:00', event = 'lab' },
+            ]",
+        );
+        let command = &config.command["nothing"];
+        let events = config.weekly_events(Day::Wednesday);
+
+        let start = at(&config, "2024-01-03", "10:00");
+        assert_eq!(config.launch_at(events[0], command, start), Some(start));
+
+        // without one, it's launched along with the notification, notify_before ahead.
+        let start = at(&config, "2024-01-03", "11:00");
+        assert_eq!(config.launch_at(events[1], command, start), None);
+        let now = at(&config, "2024-01-03", "10:30");
+        let (duration, _, event) = next_class_at(&config, now).unwrap();
+        assert_eq!(event.event, "lab");
+        assert_eq!(
+            now + Duration::from_std(duration).unwrap(),
+            start - Duration::minutes(10)
+        );
+    }
+
+    #[test]
+    fn glob_matches_stars_and_question_marks() {
+        assert!(glob_match("cs101-*", "cs101-lec1"));
+        assert!(glob_match("cs101-*", "cs101-"));
+        assert!(glob_match("cs?01-lec?", "cs201-lec2"));
+        assert!(glob_match("*lab*", "physics lab 2"));
+        assert!(!glob_match("cs101-*", "cs102-lec1"));
+        assert!(!glob_match("cs101-lec?", "cs101-lec10"));
+        assert!(!glob_match("cs101", "cs101-lec1"));
+    }
+
+    #[test]
+    fn event_mapping_precedence() {
+        let config = config(
+            "notify_before = 5
+            [events]
+            'cs101-*' = 'any'
+            'cs101-lec*' = 'lecture'
+            'cs101-lec1' = 'first'
+            'a*' = 'a'
+            '*b' = 'b'",
+        );
+        let mapping = |event| config.event_mapping(event).map(|names| names[0].as_str());
+
+        // an exact name wins over every glob.
+        assert_eq!(mapping("cs101-lec1"), Some("first"));
+        // then the longest glob.
+        assert_eq!(mapping("cs101-lec2"), Some("lecture"));
+        assert_eq!(mapping("cs101-lab"), Some("any"));
+        // equally long ones go in alphabetical order, and `*` sorts before letters.
+        assert_eq!(mapping("ab"), Some("b"));
+        assert_eq!(mapping("cs102-lec1"), None);
+        // unmapped events fall back to the default command.
+        assert_eq!(
+            config.command_names("cs102-lec1"),
+            Some(&["nothing".to_string()][..])
+        );
+    }
+
+    #[test]
+    fn semester_weeks_at_a_boundary() {
+        // week 1 is the week of 2024-01-01, a Monday, so week 7 starts on 2024-02-12.
+        let config = config(
+            "notify_before = 5
+            valid_from = '2024-01-01'
+            [timetable]
+            fri = [
+                { time = '10:00:00', event = 'lecture' },
+                { time = '16:00:00', event = 'economics', weeks = '1-6' },
+                { time = '17:00:00', event = 'economics', weeks = '7-19' },
+            ]",
+        );
+        let date = |date: &str| NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap();
+        assert_eq!(config.semester_week(date("2023-12-31")), None);
+        assert_eq!(config.semester_week(date("2024-01-01")), Some(1));
+        assert_eq!(config.semester_week(date("2024-02-11")), Some(6));
+        assert_eq!(config.semester_week(date("2024-02-12")), Some(7));
+
+        let times = |day: &str| -> Vec<_> {
+            config
+                .events_on(date(day))
+                .iter()
+                .map(|event| event.time.format("%H:%M").to_string())
+                .collect()
+        };
+        assert_eq!(times("2024-02-09"), ["10:00", "16:00"]);
+        assert_eq!(times("2024-02-16"), ["10:00", "17:00"]);
+
+        // across the boundary, the next economics is the later one.
+        let now = at(&config, "2024-02-09", "17:00");
+        let (start, _) = upcoming_at(&config, now)
+            .find(|(_, event)| event.event == "economics")
+            .unwrap();
+        assert_eq!(start, at(&config, "2024-02-16", "17:00"));
+    }
+
+    #[test]
+    fn event_without_weeks_is_always_in_them() {
+        let config = config(
+            "notify_before = 5
+            valid_from = '2024-01-01'
+            [timetable]
+            fri = [
+                { time = '10:00:00', event = 'lecture' },
+                { time = '16:00:00', event = 'economics', weeks = '1-6' },
+            ]",
+        );
+        let lecture = config.weekly_events(Day::Friday)[0];
+        let economics = config.weekly_events(Day::Friday)[1];
+        let date = |date: &str| NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap();
+        for day in ["2023-12-29", "2024-01-05", "2024-02-16", "2024-12-27"] {
+            assert!(config.in_weeks(lecture, date(day)), "{}", day);
+        }
+        assert!(config.in_weeks(economics, date("2024-02-09")));
+        assert!(!config.in_weeks(economics, date("2024-02-16")));
+        // before the semester there's no week to be in.
+        assert!(!config.in_weeks(economics, date("2023-12-29")));
+    }
+
+    #[test]
+    fn parse_duration_forms() {
+        assert_eq!(parse_duration("1h30m"), Some(Duration::minutes(90)));
+        assert_eq!(parse_duration("45s"), Some(Duration::seconds(45)));
+        assert_eq!(parse_duration("2m30s"), Some(Duration::seconds(150)));
+        // a bare number is minutes, as it always was.
+        assert_eq!(parse_duration("10"), Some(Duration::minutes(10)));
+        assert_eq!(parse_duration(" 10 "), Some(Duration::minutes(10)));
+
+        for bad in ["", "h", "10x", "1h30", "-5m", "9999999h"] {
+            assert_eq!(parse_duration(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn durations_in_the_config() {
+        let config = config(
+            "notify_before = 10
+            min_gap = '1h30m'
+            grace_after = '45s'
+            [timetable]
+            wed = [{ time = '10:00:00', event = 'lab', notify_before = '90s', launch_offset = -1 }]",
+        );
+        assert_eq!(config.notify_before, ConfigDuration(Duration::minutes(10)));
+        assert_eq!(config.min_gap, Some(ConfigDuration(Duration::minutes(90))));
+        assert_eq!(config.grace_after, ConfigDuration(Duration::seconds(45)));
+        let lab = config.weekly_events(Day::Wednesday)[0];
+        assert_eq!(config.notify_before(lab), Duration::seconds(90));
+        assert_eq!(lab.launch_offset, Some(LaunchOffset(Duration::minutes(-1))));
+
+        assert_eq!("-30s".parse(), Ok(LaunchOffset(Duration::seconds(-30))));
+        assert_eq!("-1".parse(), Ok(LaunchOffset(Duration::minutes(-1))));
+        assert_eq!("1h".parse(), Ok(LaunchOffset(Duration::hours(1))));
+    }
+
+    #[test]
+    fn aliases() {
+        let commands = |commands: &str| {
+            Config::from_toml(&format!(
+                "version = {}\ndefault_command = 'a'\nnotify_before = 5\n{}",
+                CONFIG_VERSION, commands
+            ))
+        };
+
+        let config = commands(
+            "[command.a]
+            alias = 'b'
+            args = ['--a']
+            [command.b]
+            alias = 'c'
+            args = ['--b']
+            [command.c]
+            name = 'browser'
+            args = ['--c']",
+        )
+        .unwrap();
+        assert_eq!(config.command["a"].name, "browser");
+        assert_eq!(config.command["a"].args, ["--c", "--b", "--a"]);
+        assert_eq!(config.command["a"].alias, None);
+
+        let cycle = commands(
+            "[command.a]
+            alias = 'b'
+            [command.b]
+            alias = 'a'",
+        );
+        match cycle {
+            // whichever of the two is resolved first.
+            Err(ConfigError::AliasCycle(place, chain)) => {
+                let first = &place["command.".len()..];
+                let other = if first == "a" { "b" } else { "a" };
+                assert_eq!(chain, [other, first, other]);
+            }
+            other => panic!("expected a cycle, got {:?}", other.map(|_| ())),
+        }
+
+        assert!(matches!(
+            commands("[command.a]\nalias = 'missing'"),
+            Err(ConfigError::UnknownAlias(place, target)) if place == "command.a" && target == "missing"
+        ));
+        assert!(matches!(
+            commands("[command.a]\nname = 'browser'\nalias = 'b'\n[command.b]\nname = 'b'"),
+            Err(ConfigError::NameAndAlias(place)) if place == "command.a"
+        ));
+    }
+
+    #[test]
+    fn pre_alert_notifies_ahead_and_launches_at_the_start() {
+        let config = config(
+            "notify_before = 10
+            pre_alert = true
+            [timetable]
+            wed = [
+                { time = '10:00:00', event = 'lecture' },
+                { time = '11:00:00', event = 'lab', launch_offset = -2 },
+                { time = '12:00:00', event = 'seminar', pre_alert = false },
+            ]",
+        );
+        let command = &config.command["nothing"];
+        let now = at(&config, "2024-01-03", "09:00");
+
+        // the heads-up goes out notify_before ahead of the start...
+        let (duration, _, event) = next_class_at(&config, now).unwrap();
+        assert_eq!(event.event, "lecture");
+        assert_eq!(duration, minutes(50));
+        // ...and the command is launched at the start itself.
+        let start = at(&config, "2024-01-03", "10:00");
+        assert_eq!(config.launch_at(&event, command, start), Some(start));
+
+        // a launch_offset still goes first.
+        let events = config.weekly_events(Day::Wednesday);
+        let start = at(&config, "2024-01-03", "11:00");
+        assert_eq!(
+            config.launch_at(events[1], command, start),
+            Some(at(&config, "2024-01-03", "10:58"))
+        );
+        // without pre_alert, the command is launched along with the notification.
+        let start = at(&config, "2024-01-03", "12:00");
+        assert_eq!(config.launch_at(events[2], command, start), None);
+    }
+}
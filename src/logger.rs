@@ -0,0 +1,52 @@
+//! A small stderr logger for the `log` facade that timestamps every line.
+
+use std::{env, str::FromStr};
+
+use chrono::{Local, SecondsFormat};
+use log::{LevelFilter, Log, Metadata, Record};
+
+struct Logger;
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!(
+                "{} {:<5} {}",
+                Local::now().to_rfc3339_opts(SecondsFormat::Secs, false),
+                record.level(),
+                record.args()
+            );
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// The level requested through `RUST_LOG`, either as a bare level or an `eventjoiner=level`
+/// directive among a comma separated list.
+fn env_level() -> Option<LevelFilter> {
+    let value = env::var("RUST_LOG").ok()?;
+
+    value
+        .split(',')
+        .filter_map(|directive| match directive.split_once('=') {
+            Some(("eventjoiner", level)) => LevelFilter::from_str(level.trim()).ok(),
+            Some(_) => None,
+            None => LevelFilter::from_str(directive.trim()).ok(),
+        })
+        .next_back()
+}
+
+/// Install the logger. An explicit `level` wins over `RUST_LOG`, which wins over `info`.
+pub fn init(level: Option<LevelFilter>) {
+    static LOGGER: Logger = Logger;
+
+    let level = level.or_else(env_level).unwrap_or(LevelFilter::Info);
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(level);
+    }
+}
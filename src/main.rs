@@ -1,369 +1,2569 @@
 use std::{
-    collections::HashMap, env, fmt, fs, process::Command, thread, time::Duration as StdDuration,
+    env, error, fmt, fs,
+    io::{self, Write},
+    os::unix::process::ExitStatusExt,
+    path::Path,
+    process,
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::Duration as StdDuration,
 };
 
-use chrono::{Datelike, Duration, NaiveTime};
-use clap::Parser;
+use chrono::{
+    DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, SecondsFormat, Utc,
+};
+use clap::{AppSettings, ArgEnum, IntoApp, Parser, Subcommand, ValueHint};
+use log::{debug, error, info, warn, LevelFilter};
 use notify_rust::{Notification, Timeout};
-use serde::Deserialize;
+use serde::Serialize;
+
+mod color;
+mod completions;
+mod format;
+mod ical;
+mod json;
+mod logger;
+mod state;
+mod status;
+
+use color::{paint, ColorChoice, Style};
+use completions::{NameKind, Shell};
+use eventjoiner::{
+    get_event_and_command, live_event_at, next_class_at, tz::Zone, upcoming_at, CommandArgs,
+    Config, ConfigDuration, ConfigError, Day, Event, Problem, ProblemKind, Severity, Urgency,
+    CONFIG_VERSION, MAX_LOOKAHEAD_DAYS,
+};
+use format::{Row, Template};
 
 #[derive(Parser, Debug)]
 struct Opts {
-    /// provide a custom config file, defaults to $XDG_CONFIG_HOME/classjoiner.toml
-    #[clap(short, long)]
+    /// provide a custom config file, or - to read it from stdin. Defaults to the first that
+    /// exists of ./eventjoiner.toml, $XDG_CONFIG_HOME/eventjoiner.toml,
+    /// $HOME/.config/eventjoiner.toml and /etc/eventjoiner.toml
+    #[clap(short, long, value_hint = ValueHint::FilePath)]
     config: Option<String>,
+    /// use this profile from the config instead of `default_profile`
+    #[clap(long)]
+    profile: Option<String>,
     /// launch a particular command from the config
     #[clap(
         short = 'l',
         long,
         conflicts_with("event"),
-        conflicts_with("deamonize"),
-        conflicts_with("show_command")
+        conflicts_with("daemonize"),
+        conflicts_with("show-command"),
+        conflicts_with("show-event")
     )]
     launch: Option<String>,
     /// launch a particular event from the config
     #[clap(
         short = 'e',
         long,
-        conflicts_with("command"),
-        conflicts_with("deamonize"),
-        conflicts_with("show_command")
+        conflicts_with("launch"),
+        conflicts_with("daemonize"),
+        conflicts_with("show-command"),
+        conflicts_with("show-event")
     )]
     event: Option<String>,
+    /// with --launch or --event, wait for the commands to finish one after another and exit
+    /// with the first failing one's exit code
+    #[clap(
+        long,
+        conflicts_with("daemonize"),
+        conflicts_with("launch-next"),
+        conflicts_with("until")
+    )]
+    wait: bool,
+    /// launch the next event only if it starts within this long, a number of minutes or a
+    /// duration like '90s', and exit. Meant for running from cron every minute, an event is
+    /// never launched twice
+    #[clap(
+        long,
+        value_name = "DURATION",
+        conflicts_with("launch"),
+        conflicts_with("event"),
+        conflicts_with("daemonize")
+    )]
+    until: Option<ConfigDuration>,
+    /// remembers the last launched event so that it isn't launched again by --until or a
+    /// restarted daemon, defaults to $XDG_STATE_HOME/eventjoiner/last-launch
+    #[clap(long = "state-file", value_hint = ValueHint::FilePath)]
+    state_file: Option<String>,
+    /// forget the last launched event and exit
+    #[clap(long = "reset-state")]
+    reset_state: bool,
+    /// launch the event that's on right now, or else the next one, without waiting for it
+    #[clap(
+        long = "launch-next",
+        conflicts_with("launch"),
+        conflicts_with("event"),
+        conflicts_with("daemonize")
+    )]
+    launch_next: bool,
+    /// keep running and launch each event as it comes up
     #[clap(
         short,
         long,
+        alias = "deamonize",
+        conflicts_with("launch"),
         conflicts_with("event"),
-        conflicts_with("command"),
-        conflicts_with("show_command")
+        conflicts_with("show-command"),
+        conflicts_with("show-event")
+    )]
+    daemonize: bool,
+    /// make the daemon exit once the day's or the week's last event is done, e.g. for a
+    /// systemd timer to start it again fresh
+    #[clap(
+        long = "exit-when-idle",
+        arg_enum,
+        value_name = "AFTER",
+        requires = "daemonize"
     )]
-    deamonize: bool,
-    #[clap(long = "no-run")]
+    exit_when_idle: Option<IdleAfter>,
+    /// print the commands instead of running them. With --daemonize the daemon still runs in
+    /// real time and notifies as usual, but only logs what it would launch
+    #[clap(long = "no-run", alias = "dry-run")]
     no_run: bool,
+    /// launch commands from the daemon without sending notifications, overrides
+    /// `notifications` in the config. Together with --no-run the daemon only logs. Events
+    /// with `auto_launch` off get neither a launch nor a reminder
+    #[clap(short, long, alias = "no-notify")]
+    quiet: bool,
+    /// print today's schedule
+    #[clap(long)]
+    list: bool,
+    /// make --list and --next look at the coming mon, tue, ... instead of today
+    #[clap(long, parse(try_from_str = parse_day))]
+    day: Option<Day>,
+    /// print the schedule for the whole week
+    #[clap(long = "list-all")]
+    list_all: bool,
+    /// also show disabled events with --list-all
+    #[clap(long = "show-disabled", requires = "list-all")]
+    show_disabled: bool,
+    /// pretend it's this time in the config's time zone, like 2024-05-02T08:55, to see what
+    /// --next, --list or launching would do then
+    #[clap(
+        long,
+        value_name = "DATETIME",
+        parse(try_from_str = parse_at),
+        conflicts_with("daemonize"),
+        conflicts_with("watch")
+    )]
+    at: Option<NaiveDateTime>,
+    /// print the next upcoming event and how long until it starts
+    #[clap(long)]
+    next: bool,
+    /// print only how many of today's events are yet to start, for status bars. With --json
+    /// also the next of them
+    #[clap(long, conflicts_with("list"), conflicts_with("next"))]
+    remaining: bool,
+    /// make --next print this many upcoming events, soonest first
+    #[clap(long, requires = "next")]
+    count: Option<usize>,
+    /// make --next and --watch show how long until an event rounded to the nearest multiple
+    /// of this, like "about 15m" for 15m. Takes minutes or a duration like '30s'
+    #[clap(long, value_name = "DURATION")]
+    round: Option<ConfigDuration>,
+    /// send a sample notification the way the daemon does and exit, to check whether they
+    /// work here
+    #[clap(long = "test-notify")]
+    test_notify: bool,
+    /// keep showing the next event with a live countdown, without launching anything
+    #[clap(
+        long,
+        conflicts_with("launch"),
+        conflicts_with("event"),
+        conflicts_with("daemonize"),
+        conflicts_with("launch-next"),
+        conflicts_with("until")
+    )]
+    watch: bool,
+    /// when the daemon wakes up late (e.g. after a suspend), still launch an event that
+    /// started up to this long ago, or that hasn't reached its end_time yet. Takes minutes or
+    /// a duration like '90s'
+    #[clap(long = "catchup-grace", value_name = "DURATION", default_value = "10")]
+    catchup_grace: ConfigDuration,
+    /// when daemonizing, write our PID to this file and remove it again on exit
+    #[clap(long, value_hint = ValueHint::FilePath)]
+    pidfile: Option<String>,
+    /// the socket the daemon answers `status` on, defaults to
+    /// $XDG_RUNTIME_DIR/eventjoiner.sock
+    #[clap(long, value_hint = ValueHint::FilePath)]
+    socket: Option<String>,
+    /// how much to log, overrides $RUST_LOG [default: info]
+    #[clap(
+        long = "log-level",
+        possible_values = &["error", "warn", "info", "debug", "trace"]
+    )]
+    log_level: Option<LevelFilter>,
+    /// refuse to run with events that overlap on the same day, instead of warning about them.
+    /// Makes validate fail on warnings as well
+    #[clap(long, global = true)]
+    strict: bool,
+    /// color --next, --list, --list-all, --sc and --show-event output. auto colors it when
+    /// printing to a terminal and NO_COLOR isn't set
+    #[clap(long, arg_enum, value_name = "WHEN", default_value = "auto")]
+    color: ColorChoice,
+    /// print --next, --list, --list-all, --sc, --show-event and validate output as JSON
+    #[clap(long, global = true)]
+    json: bool,
+    /// print each event of --list, --list-all and --next as a line filled in from this
+    /// template, like "{event} at {time}". It can use {event}, {time}, {end_time}, {date},
+    /// {weekday}, {command} and {countdown}, {{ and }} are plain braces
+    #[clap(long, value_name = "TEMPLATE", conflicts_with("json"))]
+    format: Option<Template>,
+    /// print a command, or every command an event launches
     #[clap(
         long = "sc",
+        conflicts_with("launch"),
         conflicts_with("event"),
-        conflicts_with("command"),
-        conflicts_with("deamonize")
+        conflicts_with("daemonize"),
+        conflicts_with("show-event")
     )]
     show_command: Option<String>,
+    /// print every command an event launches
+    #[clap(
+        long = "show-event",
+        conflicts_with("launch"),
+        conflicts_with("event"),
+        conflicts_with("daemonize"),
+        conflicts_with("show-command")
+    )]
+    show_event: Option<String>,
+    #[clap(subcommand)]
+    subcommand: Option<SubCommand>,
 }
 
-/// The config as read from the config file.
-#[derive(Debug, Deserialize, Clone)]
-struct Config {
-    /// Maps weekdays to  vectors of scheduled events for that day.
-    timetable: HashMap<Day, Vec<Event>>,
-    /// Maps a particular event to a command name to run when it's time for that event.
-    events: HashMap<String, String>,
-    /// Maps command names to actual command.
-    command: HashMap<String, CommandArgs>,
-    /// How much time before notifying for event in minutes
-    notify_before: u32,
-}
-
-/// Represents a command to launch when time for event.
-#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
-struct CommandArgs {
-    /// Name of the binary to run.
-    name: String,
-    /// Arguments to pass to that binary.
-    args: Vec<String>,
-}
-
-/// A particular event in a day.
-#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
-struct Event {
-    /// At which hour (from 0 to 23) does the event occur.
-    time: NaiveTime,
-    /// The event to launch at this event.
-    event: String,
+/// After which last event `--exit-when-idle` stops the daemon.
+#[derive(ArgEnum, Debug, Clone, Copy)]
+enum IdleAfter {
+    Day,
+    Week,
+}
+
+#[derive(Subcommand, Debug)]
+enum SubCommand {
+    /// check the config for dangling references and overlapping events
+    Validate,
+    /// write a commented starter config to the config path
+    Init {
+        /// overwrite the config if it already exists
+        #[clap(long)]
+        force: bool,
+    },
+    /// print a timetable converted from an iCalendar (.ics) file, weekly events become
+    /// timetable entries and the rest one-off events
+    ImportIcal {
+        #[clap(value_hint = ValueHint::FilePath)]
+        file: String,
+    },
+    /// print the timetable and one-off events as an iCalendar (.ics) file
+    ExportIcal,
+    /// write a systemd user service that runs the daemon, for starting it on login
+    InstallSystemd {
+        /// overwrite the service if it already exists
+        #[clap(long)]
+        force: bool,
+    },
+    /// remove the service written by install-systemd
+    UninstallSystemd,
+    /// ask the running daemon which event it is waiting for
+    Status,
+    /// print the absolute path of the config that would be read, failing if there is none
+    ConfigPath,
+    /// print only when the daemon would next spawn a command, for scripts; prints nothing
+    /// and exits with 7 when nothing is coming up that it would launch
+    NextLaunchTime,
+    /// print a completion script for the given shell
+    Completions {
+        #[clap(arg_enum)]
+        shell: Shell,
+    },
+    /// print the names of commands or events in the config, for completion scripts
+    #[clap(setting = AppSettings::Hidden)]
+    Names {
+        #[clap(arg_enum, required = true)]
+        kinds: Vec<NameKind>,
+    },
 }
 
-#[derive(Debug, Deserialize, Clone, Copy, Hash, PartialEq, Eq)]
-#[serde(try_from = "String")]
-enum Day {
-    Monday,
-    Teusday,
-    Wednesday,
-    Thursday,
-    Friday,
-    Saturday,
-    Sunday,
+/// The starter config written by `init`.
+const TEMPLATE: &str = include_str!("template.toml");
+
+/// Everything that can go wrong while running, each mapped to its own exit code.
+#[derive(Debug)]
+enum AppError {
+    /// Neither `--config`, `$XDG_CONFIG_HOME` nor `$HOME` was given.
+    NoConfigPath,
+    /// The config file at this path couldn't be read.
+    ReadConfig(String, io::Error),
+    /// The calendar at this path couldn't be read.
+    ReadIcal(String, io::Error),
+    /// The calendar couldn't be converted.
+    ParseIcal(String),
+    /// The systemd unit at this path couldn't be written.
+    WriteUnit(String, io::Error),
+    /// The systemd unit at this path couldn't be removed.
+    RemoveUnit(String, io::Error),
+    /// There already is a systemd unit at this path.
+    UnitExists(String),
+    /// The config file at this path couldn't be written.
+    WriteConfig(String, io::Error),
+    /// `init` would overwrite the config at this path.
+    ConfigExists(String),
+    /// The config file isn't valid TOML or doesn't have the expected shape.
+    ParseConfig(toml::de::Error),
+    /// The config parsed fine but is inconsistent.
+    InvalidConfig(String),
+    /// There is no command with this name.
+    UnknownCommand(String),
+    /// There is no event with this name.
+    UnknownEvent(String),
+    /// The event maps to a command that doesn't exist.
+    MissingCommand { event: String, command: String },
+    /// The command could not be spawned.
+    Launch(String, io::Error),
+    /// The command was waited for and didn't exit successfully.
+    Exited(String, process::ExitStatus),
+    /// The desktop notification could not be shown.
+    Notify(notify_rust::error::Error),
+    /// The timetable has nothing to wait for.
+    NoSchedule,
+    /// Neither the timetable nor the one-off events have any events.
+    NoEvents,
+    /// The PID file at this path couldn't be read or written.
+    PidFile(String, io::Error),
+    /// Neither `$XDG_STATE_HOME` nor `$HOME` was given.
+    NoStatePath,
+    /// The state file at this path couldn't be written or removed.
+    State(String, io::Error),
+    /// Another daemon with this PID is already running.
+    AlreadyRunning(i32),
+    /// The daemon's socket at this path couldn't be queried.
+    Status(String, io::Error),
 }
 
-impl Day {
-    fn next(&self) -> Self {
-        use Day::*;
+impl AppError {
+    /// The process exit code to report this error with.
+    fn exit_code(&self) -> i32 {
+        use AppError::*;
 
         match self {
-            Monday => Teusday,
-            Teusday => Wednesday,
-            Wednesday => Thursday,
-            Thursday => Friday,
-            Friday => Saturday,
-            Saturday => Sunday,
-            Sunday => Monday,
+            NoConfigPath | ReadConfig(..) | ReadIcal(..) | WriteConfig(..) | ConfigExists(_) => 2,
+            WriteUnit(..) | RemoveUnit(..) | UnitExists(_) => 2,
+            NoStatePath | State(..) => 2,
+            ParseConfig(_) | InvalidConfig(_) | ParseIcal(_) => 3,
+            UnknownCommand(_) | UnknownEvent(_) | MissingCommand { .. } => 4,
+            Launch(..) => 5,
+            // like a shell would report it, so scripts can tell the command failed.
+            Exited(_, status) => status
+                .code()
+                .unwrap_or_else(|| 128 + status.signal().unwrap_or_default()),
+            Notify(_) => 6,
+            NoSchedule | NoEvents => 7,
+            PidFile(..) | AlreadyRunning(_) | Status(..) => 8,
         }
     }
 }
 
-impl fmt::Display for CommandArgs {
+impl fmt::Display for AppError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{} ", self.name)?;
-        for arg in &self.args {
-            write!(f, "{} ", arg)?;
+        use AppError::*;
+
+        match self {
+            NoConfigPath => write!(
+                f,
+                "neither $XDG_CONFIG_HOME nor $HOME set, unable to find config (use --config)"
+            ),
+            ReadConfig(path, e) => write!(f, "unable to read config {}: {}", path, e),
+            ReadIcal(path, e) => write!(f, "unable to read calendar {}: {}", path, e),
+            ParseIcal(e) => write!(f, "unable to convert calendar: {}", e),
+            WriteConfig(path, e) => write!(f, "unable to write config {}: {}", path, e),
+            WriteUnit(path, e) => write!(f, "unable to write unit {}: {}", path, e),
+            RemoveUnit(path, e) => write!(f, "unable to remove unit {}: {}", path, e),
+            UnitExists(path) => {
+                write!(f, "unit {} already exists (use --force to overwrite)", path)
+            }
+            ConfigExists(path) => {
+                write!(
+                    f,
+                    "config {} already exists (use --force to overwrite)",
+                    path
+                )
+            }
+            ParseConfig(e) => write!(f, "unable to parse config: {}", e),
+            InvalidConfig(e) => write!(f, "invalid config: {}", e),
+            UnknownCommand(command) => write!(f, "invalid command {}", command),
+            UnknownEvent(event) => write!(f, "invalid event {}", event),
+            MissingCommand { event, command } => {
+                write!(f, "event {} maps to unknown command {}", event, command)
+            }
+            Launch(name, e) => write!(f, "unable to launch {}: {}", name, e),
+            Exited(name, status) => write!(f, "{} exited with {}", name, status),
+            Notify(e) => write!(f, "unable to show notification: {}", e),
+            NoSchedule => write!(f, "no schedule set"),
+            NoEvents => write!(
+                f,
+                "no events configured, add some to the timetable or as one-off events"
+            ),
+            NoStatePath => write!(
+                f,
+                "neither $XDG_STATE_HOME nor $HOME set, unable to find the state file"
+            ),
+            State(path, e) => write!(f, "unable to update state file {}: {}", path, e),
+            PidFile(path, e) => write!(f, "unable to write pidfile {}: {}", path, e),
+            AlreadyRunning(pid) => write!(f, "daemon already running with pid {}", pid),
+            Status(path, e) => write!(f, "unable to reach daemon at {}: {}", path, e),
+        }
+    }
+}
+
+impl error::Error for AppError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        use AppError::*;
+
+        match self {
+            ReadConfig(_, e)
+            | ReadIcal(_, e)
+            | WriteConfig(_, e)
+            | WriteUnit(_, e)
+            | RemoveUnit(_, e)
+            | Launch(_, e)
+            | PidFile(_, e)
+            | State(_, e)
+            | Status(_, e) => Some(e),
+            ParseConfig(e) => Some(e),
+            Notify(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// A row of the `--list`/`--list-all` output.
+#[derive(Debug, Serialize)]
+struct ListEntry<'a> {
+    day: String,
+    event: &'a str,
+    time: NaiveTime,
+    end_time: Option<NaiveTime>,
+    /// whether the event is already over.
+    done: bool,
+    enabled: bool,
+    commands: Vec<&'a CommandArgs>,
+}
+
+/// `--next` output.
+#[derive(Debug, Serialize)]
+struct NextOutput<'a> {
+    event: &'a str,
+    /// start of the event as an RFC 3339 datetime.
+    start: String,
+    seconds_until: u64,
+    commands: Vec<&'a CommandArgs>,
+}
+
+/// `--remaining` output.
+#[derive(Debug, Serialize)]
+struct RemainingOutput<'a> {
+    remaining: usize,
+    next: Option<&'a str>,
+}
+
+/// `status` output.
+#[derive(Debug, Serialize)]
+struct StatusOutput<'a> {
+    event: &'a str,
+    /// start of the event as an RFC 3339 datetime.
+    start: &'a str,
+    seconds_until: u64,
+}
+
+/// Rows for `events` on `day`, marking the ones that are over by `now` as done.
+fn list_entries<'a>(
+    config: &'a Config,
+    day: Day,
+    events: Vec<&'a Event>,
+    now: Option<NaiveTime>,
+) -> Vec<ListEntry<'a>> {
+    events
+        .into_iter()
+        .map(|event| ListEntry {
+            day: day.to_string(),
+            event: &event.event,
+            time: event.time,
+            end_time: event.end_time,
+            // an event running past midnight isn't over until tomorrow.
+            done: match (now, event.end_time) {
+                (Some(now), Some(end)) => event.time < end && end <= now,
+                (Some(now), None) => event.time <= now,
+                (None, _) => false,
+            },
+            enabled: event.enabled,
+            commands: config.commands_for(event),
+        })
+        .collect()
+}
+
+fn print_entries(entries: &[ListEntry]) {
+    for entry in entries {
+        let markers = format!(
+            "{}{}",
+            if entry.enabled { "" } else { "[disabled] " },
+            if entry.done { "[done] " } else { "" },
+        );
+        // rows that don't matter anymore are dimmed as a whole.
+        let dim = entry.done || !entry.enabled;
+        let row = |command: &dyn fmt::Display| {
+            if dim {
+                paint(
+                    Style::Dim,
+                    format!("  {}{} {} -> {}", markers, entry.time, entry.event, command),
+                )
+            } else {
+                format!(
+                    "  {}{} {} -> {}",
+                    markers,
+                    paint(Style::Time, entry.time),
+                    paint(Style::Event, entry.event),
+                    paint(Style::Command, command)
+                )
+            }
+        };
+        if entry.commands.is_empty() {
+            println!("{}", row(&"<no command>"));
+        }
+        // one row per command, so every line reads on its own.
+        for command in &entry.commands {
+            print!("{}", row(command));
         }
-        writeln!(f, "")
     }
 }
 
-impl TryFrom<String> for Day {
-    type Error = String;
+/// What `--format` is filled in with for `event`, on `date` unless it's about `day` in
+/// general.
+fn format_row<'a>(
+    config: &'a Config,
+    event: &'a Event,
+    day: Day,
+    date: Option<NaiveDate>,
+) -> Row<'a> {
+    Row {
+        event: &event.event,
+        time: event.time,
+        end_time: event.end_time,
+        date,
+        day,
+        // an event's own command has no name, the binary stands in for it.
+        commands: match &event.command {
+            Some(command) => std::slice::from_ref(&command.name),
+            None => config.command_names(&event.event).unwrap_or_default(),
+        },
+        // to_std fails once the event has started.
+        countdown: date.and_then(|date| (config.at(date, event.time) - config.now()).to_std().ok()),
+    }
+}
+
+fn print_json<T: Serialize>(value: &T) {
+    println!(
+        "{}",
+        json::to_string(value).expect("unable to serialize output")
+    );
+}
+
+fn parse_day(day: &str) -> Result<Day, String> {
+    Day::try_from(day.to_string())
+}
+
+/// Accepts `2024-05-02T08:55`, with or without seconds, and with a space instead of the `T`.
+fn parse_at(at: &str) -> Result<NaiveDateTime, String> {
+    [
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%d %H:%M",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(at.trim(), format).ok())
+    .ok_or_else(|| format!("invalid datetime {}, expected e.g. 2024-05-02T08:55", at))
+}
+
+/// The date --list and --next are about: the next `day` from today on, or else today.
+fn query_date(config: &Config, day: Option<Day>) -> NaiveDate {
+    let today = config.now().date().naive_local();
+    match day {
+        Some(day) => (0..7)
+            .map(|days| today + Duration::days(days))
+            .find(|date| Day::from(date.weekday()) == day)
+            .unwrap(),
+        None => today,
+    }
+}
+
+/// When `event` starts, given that next_class said to notify for it in `duration` from `now`.
+fn event_start(
+    config: &Config,
+    now: DateTime<Zone>,
+    duration: StdDuration,
+    event: &Event,
+) -> DateTime<Zone> {
+    // next_class counts down to the notification rather than the start, and bottoms out at
+    // zero once we're inside the notification window. The event hasn't started yet then, so
+    // it's the next time its start comes around: today, or tomorrow when the window reaches
+    // back across midnight.
+    if duration.is_zero() {
+        let today = now.date().naive_local();
+        let start = config.at(today, event.time);
+        return if start >= now {
+            start
+        } else {
+            config.at(today.succ(), event.time)
+        };
+    }
+
+    let start = now + Duration::from_std(duration).unwrap() + config.notify_before(event);
+    config.at(start.date().naive_local(), event.time)
+}
 
-    fn try_from(value: String) -> Result<Self, Self::Error> {
-        use Day::*;
+/// When the daemon next spawns a command, going through the schedule from `now` the way it
+/// does: waking up for each event at its notification, min_gap after the last one, and
+/// launching each command then or at its launch time. Events it wouldn't launch, with
+/// auto_launch off or already launched going by the state file at `state_path`, are passed
+/// over.
+fn next_launch_time(
+    config: &Config,
+    now: DateTime<Zone>,
+    state_path: Option<&str>,
+) -> Option<DateTime<Zone>> {
+    let mut from = now;
+    let mut last_launch: Option<DateTime<Zone>> = None;
+    let mut first: Option<DateTime<Zone>> = None;
+    while let Some((duration, commands, schedule)) = next_class_at(config, from) {
+        let start = event_start(config, from, duration, &schedule);
+        let mut notify_at = start - config.notify_before(&schedule);
+        if let (Some(last), Some(gap)) = (last_launch, config.min_gap) {
+            notify_at = notify_at.max(last + gap.0).min(start);
+        }
+        // nothing launches before the daemon wakes up for it.
+        if first.is_some_and(|first| notify_at >= first)
+            || start - now > Duration::days(MAX_LOOKAHEAD_DAYS)
+        {
+            break;
+        }
 
-        match value.as_str() {
-            "mon" => Ok(Monday),
-            "teu" => Ok(Teusday),
-            "wed" => Ok(Wednesday),
-            "thu" => Ok(Thursday),
-            "fri" => Ok(Friday),
-            "sat" => Ok(Saturday),
-            "sun" => Ok(Sunday),
-            _ => Err(format!("invalid day {}", value)),
+        let woke = notify_at.max(now);
+        let launched = state_path.is_some_and(|path| state::launched(path, &schedule.event, start));
+        if !launched {
+            last_launch = Some(woke);
+            if config.auto_launch(&schedule) {
+                let at = commands
+                    .iter()
+                    .map(|&command| {
+                        config
+                            .launch_at(&schedule, command, start)
+                            .filter(|at| *at > woke)
+                            .unwrap_or(woke)
+                    })
+                    .min();
+                first = first.into_iter().chain(at).min();
+            }
         }
+
+        from = config
+            .end_at(start.date().naive_local(), &schedule)
+            .unwrap_or(start + Duration::seconds(1))
+            .max(woke);
+    }
+    first
+}
+
+/// format a duration as a human countdown like "1d 2h 5m", rounded down to the minute.
+fn format_countdown(duration: StdDuration) -> String {
+    let minutes = duration.as_secs() / 60;
+    let (days, hours, minutes) = (minutes / (24 * 60), minutes / 60 % 24, minutes % 60);
+
+    let mut out = String::new();
+    if days > 0 {
+        out.push_str(&format!("{}d ", days));
+    }
+    if days > 0 || hours > 0 {
+        out.push_str(&format!("{}h ", hours));
+    }
+    out.push_str(&format!("{}m", minutes));
+
+    out
+}
+
+/// format a duration as a countdown rounded to the nearest multiple of `unit`, like
+/// "about 15m", for --round.
+fn format_rounded(duration: StdDuration, unit: ConfigDuration) -> String {
+    let unit = unit.0.num_seconds().max(1) as u64;
+    let rounded = (duration.as_secs() + unit / 2) / unit * unit;
+    format!(
+        "about {}",
+        format_countdown(StdDuration::from_secs(rounded))
+    )
+}
+
+/// format a duration as a clock like "2:05:09", with days in front like "1d 2:05:09".
+fn format_clock(duration: StdDuration) -> String {
+    let seconds = duration.as_secs();
+    let (days, hours, minutes, seconds) = (
+        seconds / (24 * 60 * 60),
+        seconds / (60 * 60) % 24,
+        seconds / 60 % 60,
+        seconds % 60,
+    );
+
+    if days > 0 {
+        format!("{}d {}:{:02}:{:02}", days, hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
     }
 }
 
-impl From<chrono::Weekday> for Day {
-    fn from(day: chrono::Weekday) -> Self {
-        use chrono::Weekday::*;
+/// The config file in the XDG config directory, defaulting to `$HOME/.config` as the spec says.
+fn default_config_path() -> Result<String, AppError> {
+    Ok(format!("{}/eventjoiner.toml", config_home()?))
+}
+
+/// The first config file that exists out of the current directory, `$XDG_CONFIG_HOME`,
+/// `$HOME/.config` and `/etc`. With none of them there, the one in the XDG config directory,
+/// so errors point at where it's expected.
+fn find_config_path() -> Result<String, AppError> {
+    let mut candidates = vec!["./eventjoiner.toml".to_string()];
+    if let Ok(dir) = env::var("XDG_CONFIG_HOME") {
+        if !dir.is_empty() {
+            candidates.push(format!("{}/eventjoiner.toml", dir));
+        }
+    }
+    if let Ok(home) = env::var("HOME") {
+        if !home.is_empty() {
+            candidates.push(format!("{}/.config/eventjoiner.toml", home));
+        }
+    }
+    candidates.push("/etc/eventjoiner.toml".to_string());
 
-        match day {
-            Mon => Day::Monday,
-            Tue => Day::Teusday,
-            Wed => Day::Wednesday,
-            Thu => Day::Thursday,
-            Fri => Day::Friday,
-            Sat => Day::Saturday,
-            Sun => Day::Sunday,
+    match candidates
+        .into_iter()
+        .find(|path| Path::new(path).is_file())
+    {
+        Some(path) => {
+            info!("using config {}", path);
+            Ok(path)
         }
+        None => default_config_path(),
     }
 }
 
-fn compare_events(a: &Event, b: &Event) -> std::cmp::Ordering {
-    a.time.cmp(&b.time)
+/// `$XDG_CONFIG_HOME`, or else `$HOME/.config`.
+fn config_home() -> Result<String, AppError> {
+    match env::var("XDG_CONFIG_HOME") {
+        Ok(dir) if !dir.is_empty() => Ok(dir),
+        _ => match env::var("HOME") {
+            Ok(home) if !home.is_empty() => Ok(format!("{}/.config", home)),
+            _ => Err(AppError::NoConfigPath),
+        },
+    }
 }
 
-/// get event and command for today.
-fn get_event_and_command(config: &Config) -> Option<(Event, &CommandArgs)> {
-    let now = chrono::Local::now();
-    let time_now = now.time();
+/// The commands an event named `event` launches, failing on unknown names. A scheduled
+/// event's own `command` comes first. Events without an entry in `events` only fall back to
+/// `default_command` when they're scheduled, so a typo doesn't launch it.
+fn resolve_event<'a>(config: &'a Config, event: &str) -> Result<Vec<&'a CommandArgs>, AppError> {
+    if let Some(command) = config.inline_command(event) {
+        return Ok(vec![command]);
+    }
 
-    let mut events = config.timetable.get(&Day::from(now.weekday()))?.clone();
+    let names = config
+        .command_names(event)
+        .filter(|_| config.event_mapping(event).is_some() || config.is_scheduled(event))
+        .ok_or_else(|| AppError::UnknownEvent(event.to_string()))?;
 
-    events.sort_by(compare_events);
+    names
+        .iter()
+        .map(|name| {
+            config
+                .command
+                .get(name)
+                .ok_or_else(|| AppError::MissingCommand {
+                    event: event.to_string(),
+                    command: name.clone(),
+                })
+        })
+        .collect()
+}
 
-    match events.binary_search_by(|s| s.time.cmp(&time_now)) {
-        Ok(idx) | Err(idx) if idx < events.len() => {
-            if (events[idx].time - time_now) > Duration::minutes(config.notify_before as i64) {
-                Some((
-                    events[idx].clone(),
-                    config
-                        .command
-                        .get(config.events.get(&events[idx].event).unwrap())
-                        .unwrap(),
-                ))
-            } else {
-                None
+/// Print what `validate` found, as lines or as a JSON list, failing on any error, or with
+/// `strict` on any warning too.
+fn report_problems(problems: &[Problem], json: bool, strict: bool) -> Result<(), AppError> {
+    if json {
+        print_json(&problems);
+    } else {
+        for problem in problems {
+            match problem.severity {
+                Severity::Error => println!("{}", problem),
+                Severity::Warning => println!("warning: {}", problem),
+            }
+        }
+    }
+
+    let failed = problems
+        .iter()
+        .filter(|problem| strict || problem.severity == Severity::Error)
+        .count();
+    match failed {
+        0 => {
+            if !json {
+                println!("config ok");
             }
+            Ok(())
         }
-        _ => None,
+        n => Err(AppError::InvalidConfig(format!("{} problem(s) found", n))),
     }
 }
 
-/// get duration to sleep till next class, as well as command and event.
-fn next_class(config: &Config) -> Option<(StdDuration, &CommandArgs, Event)> {
-    let now = chrono::Local::now();
-    let time_now = now.time();
-    let mut cur_day = Day::from(now.weekday());
+/// `e`, which kept the config from loading, as a problem for `validate --json`.
+fn load_problem(e: &AppError) -> Problem {
+    let message = e.to_string();
+    // toml only tells which key it was in its message, like "for key `timetable`".
+    let key = message
+        .split_once("for key `")
+        .and_then(|(_, rest)| rest.split_once('`'))
+        .map_or_else(String::new, |(key, _)| key.to_string());
+    // Day's own error, see its TryFrom.
+    let kind = if message.contains("invalid day ") {
+        ProblemKind::UnknownDay
+    } else {
+        ProblemKind::Load
+    };
+    Problem::new(kind, key, message)
+}
 
-    if let Some(events) = config.timetable.get(&cur_day) {
-        let mut events = events.clone();
-        events.sort_by(compare_events);
+/// read and parse the config file at `path`, or stdin if it's `-`, switching to `profile` if
+/// given.
+fn read_config(path: &str, profile: Option<&str>) -> Result<Config, AppError> {
+    let contents = if path == "-" {
+        io::read_to_string(io::stdin())
+    } else {
+        fs::read_to_string(path)
+    }
+    .map_err(|e| AppError::ReadConfig(path.to_string(), e))?;
 
-        match events.binary_search_by(|a| a.time.cmp(&time_now)) {
-            Ok(idx) | Err(idx) if idx < events.len() => {
-                let event = &events[idx];
-                let notify_time = event.time - Duration::minutes(config.notify_before as i64);
-                if notify_time <= time_now {
-                    return Some((
-                        StdDuration::from_secs(0),
-                        config
-                            .command
-                            .get(config.events.get(&event.event).unwrap())
-                            .unwrap(),
-                        event.clone(),
-                    ));
-                } else {
-                    return Some((
-                        (notify_time - time_now).to_std().unwrap(),
-                        config
-                            .command
-                            .get(config.events.get(&event.event).unwrap())
-                            .unwrap(),
-                        event.clone(),
-                    ));
+    let mut config = Config::from_toml(&contents)?;
+    for change in &config.migrated {
+        info!(
+            "migrated {} to config version {}: {}",
+            path, CONFIG_VERSION, change
+        );
+    }
+    config.select_profile(profile)?;
+    Ok(config)
+}
+
+impl From<ConfigError> for AppError {
+    fn from(e: ConfigError) -> Self {
+        match e {
+            ConfigError::Parse(e) => AppError::ParseConfig(e),
+            e @ (ConfigError::UnknownTimezone(..)
+            | ConfigError::UnknownProfile(_)
+            | ConfigError::UnsupportedVersion(_)
+            | ConfigError::UnsetVariable(..)
+            | ConfigError::UnknownAlias(..)
+            | ConfigError::AliasCycle(..)
+            | ConfigError::MissingName(_)
+            | ConfigError::NameAndAlias(_)) => AppError::InvalidConfig(e.to_string()),
+        }
+    }
+}
+
+/// write the starter config to `path`, creating its directory if needed.
+fn write_template(path: &str, force: bool) -> Result<(), AppError> {
+    if !force && Path::new(path).exists() {
+        return Err(AppError::ConfigExists(path.to_string()));
+    }
+
+    if let Some(dir) = Path::new(path).parent() {
+        fs::create_dir_all(dir).map_err(|e| AppError::WriteConfig(path.to_string(), e))?;
+    }
+    fs::write(path, TEMPLATE).map_err(|e| AppError::WriteConfig(path.to_string(), e))?;
+
+    println!("wrote starter config to {}", path);
+    Ok(())
+}
+
+/// Name of the systemd user unit `install-systemd` writes.
+const UNIT_NAME: &str = "eventjoiner.service";
+
+/// Quote `arg` for an `ExecStart=` line if it needs it.
+fn systemd_quote(arg: &str) -> String {
+    if arg.is_empty() || arg.contains(|c: char| c.is_whitespace() || "\"'\\$%;".contains(c)) {
+        format!(
+            "\"{}\"",
+            arg.replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('$', "$$")
+                .replace('%', "%%")
+        )
+    } else {
+        arg.to_string()
+    }
+}
+
+/// Write a systemd user unit that runs this binary as a daemon on `config_path`.
+fn install_systemd(config_path: &str, force: bool) -> Result<(), AppError> {
+    let dir = format!("{}/systemd/user", config_home()?);
+    let path = format!("{}/{}", dir, UNIT_NAME);
+    if !force && Path::new(&path).exists() {
+        return Err(AppError::UnitExists(path));
+    }
+
+    let exe = env::current_exe().map_err(|e| AppError::WriteUnit(path.clone(), e))?;
+    // the unit doesn't run in our working directory.
+    let config_path = fs::canonicalize(config_path)
+        .map(|path| path.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| config_path.to_string());
+    let unit = format!(
+        "[Unit]\n\
+         Description=launch the events from the eventjoiner timetable\n\
+         \n\
+         [Service]\n\
+         ExecStart={} --config {} --daemonize\n\
+         ExecReload=/bin/kill -HUP $MAINPID\n\
+         Restart=on-failure\n\
+         \n\
+         [Install]\n\
+         WantedBy=default.target\n",
+        systemd_quote(&exe.to_string_lossy()),
+        systemd_quote(&config_path)
+    );
+
+    fs::create_dir_all(&dir).map_err(|e| AppError::WriteUnit(path.clone(), e))?;
+    fs::write(&path, unit).map_err(|e| AppError::WriteUnit(path.clone(), e))?;
+
+    println!("wrote {}, start it now and on every login with", path);
+    println!("    systemctl --user daemon-reload");
+    println!("    systemctl --user enable --now {}", UNIT_NAME);
+    Ok(())
+}
+
+/// Remove the unit written by `install_systemd`, along with the link enabling it.
+fn uninstall_systemd() -> Result<(), AppError> {
+    let dir = format!("{}/systemd/user", config_home()?);
+    let path = format!("{}/{}", dir, UNIT_NAME);
+
+    fs::remove_file(&path).map_err(|e| AppError::RemoveUnit(path.clone(), e))?;
+    let wants = format!("{}/default.target.wants/{}", dir, UNIT_NAME);
+    match fs::remove_file(&wants) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => {
+            return Err(AppError::RemoveUnit(wants, e))
+        }
+        _ => {}
+    }
+
+    println!("removed {}, stop a running daemon with", path);
+    println!("    systemctl --user stop {}", UNIT_NAME);
+    println!("    systemctl --user daemon-reload");
+    Ok(())
+}
+
+/// Set when the daemon receives SIGHUP and should re-read its config.
+static RELOAD: AtomicBool = AtomicBool::new(false);
+/// Set when the daemon receives SIGTERM or SIGINT and should exit.
+static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_sighup(_: libc::c_int) {
+    RELOAD.store(true, Ordering::SeqCst);
+}
+
+extern "C" fn on_shutdown(_: libc::c_int) {
+    SHUTDOWN.store(true, Ordering::SeqCst);
+}
+
+fn install_signal_handler(signal: libc::c_int, handler: extern "C" fn(libc::c_int)) {
+    // SAFETY: our handlers only store to an atomic, which is async-signal-safe.
+    unsafe {
+        libc::signal(signal, handler as libc::sighandler_t);
+    }
+}
+
+/// A PID file that is removed again when dropped.
+struct PidFile(String);
+
+impl PidFile {
+    /// Write our PID to `path`, refusing if it names a process that is still alive.
+    fn create(path: &str) -> Result<Self, AppError> {
+        if let Ok(contents) = fs::read_to_string(path) {
+            // 0 and negative PIDs name process groups, so such a file can only be stale.
+            if let Some(pid) = contents.trim().parse::<i32>().ok().filter(|&pid| pid > 0) {
+                // SAFETY: signal 0 only checks whether the process exists.
+                let alive = unsafe { libc::kill(pid, 0) } == 0
+                    || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM);
+                if alive {
+                    return Err(AppError::AlreadyRunning(pid));
                 }
             }
-            _ => {}
         }
+
+        fs::write(path, format!("{}\n", process::id()))
+            .map_err(|e| AppError::PidFile(path.to_string(), e))?;
+
+        Ok(PidFile(path.to_string()))
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// `at` in the system's local time zone, for the daemon's log.
+fn local_time(at: DateTime<Zone>) -> String {
+    at.with_timezone(&Local)
+        .to_rfc3339_opts(SecondsFormat::Secs, false)
+}
+
+/// How often a sleeping daemon checks the clock and whether it was asked to reload or
+/// shut down.
+const POLL_INTERVAL: StdDuration = StdDuration::from_secs(1);
+
+/// Sleep until the wall clock reaches `deadline`, waking up early if asked to reload or shut
+/// down. Returns whether the deadline was reached.
+///
+/// Sleeping in short slices against the wall clock, rather than one long `thread::sleep`,
+/// keeps signals responsive and means time spent suspended counts towards the deadline.
+fn sleep_until(deadline: DateTime<Zone>) -> bool {
+    loop {
+        if RELOAD.load(Ordering::SeqCst) || SHUTDOWN.load(Ordering::SeqCst) {
+            return false;
+        }
+
+        let remaining = match deadline.signed_duration_since(Utc::now()).to_std() {
+            Ok(remaining) if !remaining.is_zero() => remaining,
+            _ => return true,
+        };
+        thread::sleep(remaining.min(POLL_INTERVAL));
     }
+}
 
-    for diff in 1..=6 {
-        cur_day = cur_day.next();
+/// Like sleep_until, but only shutting down cuts it short, for waits that a reload
+/// shouldn't call off.
+fn sleep_through_reloads(deadline: DateTime<Zone>) -> bool {
+    loop {
+        if SHUTDOWN.load(Ordering::SeqCst) {
+            return false;
+        }
 
-        let events = match config.timetable.get(&cur_day) {
-            Some(v) => v,
-            None => continue,
+        let remaining = match deadline.signed_duration_since(Utc::now()).to_std() {
+            Ok(remaining) if !remaining.is_zero() => remaining,
+            _ => return true,
         };
-        let event = events
-            .into_iter()
-            .min_by(|a, b| a.time.cmp(&b.time))
-            .unwrap();
+        thread::sleep(remaining.min(POLL_INTERVAL));
+    }
+}
+
+fn main() {
+    let opts = Opts::parse();
 
-        let notify_time = event.time - Duration::minutes(config.notify_before as i64);
+    logger::init(opts.log_level);
+    color::init(opts.color);
+
+    if env::args().any(|arg| arg == "--deamonize") {
+        warn!("--deamonize is deprecated, use --daemonize instead");
+    }
+
+    if let Err(e) = run(opts) {
+        eprintln!("error: {}", e);
+        process::exit(e.exit_code());
+    }
+}
 
-        let duration = if notify_time > time_now {
-            Duration::days(diff) + (notify_time - time_now)
+/// The notification for launching `event`. Each setting comes from the first of the
+/// event's `commands` that has it.
+fn notification(
+    event: &Event,
+    commands: &[&CommandArgs],
+    late: bool,
+    launched: bool,
+) -> Notification {
+    let body = commands
+        .iter()
+        .find_map(|command| command.notify_body.as_deref())
+        .unwrap_or(if launched {
+            "class launched"
         } else {
-            Duration::days(diff - 1) + (Duration::days(1) - (time_now - notify_time))
-        };
+            "class at {time}"
+        })
+        .replace("{event}", &event.event)
+        .replace("{time}", &event.time.format("%H:%M").to_string());
 
-        return Some((
-            duration.to_std().unwrap(),
-            config
-                .command
-                .get(config.events.get(&event.event).unwrap())
-                .unwrap(),
-            event.clone(),
+    let mut notification = Notification::new();
+    notification
+        .summary(&format!("{} - ClassJoiner", event.event))
+        .body(&if late {
+            format!("{} (late)", body)
+        } else {
+            body
+        })
+        .timeout(Timeout::Milliseconds(
+            commands
+                .iter()
+                .find_map(|command| command.notify_timeout_ms)
+                .unwrap_or(6000),
         ));
+
+    if let Some(icon) = commands.iter().find_map(|command| command.icon.as_deref()) {
+        notification.icon(icon);
+    }
+    if let Some(urgency) = commands.iter().find_map(|command| command.notify_urgency) {
+        notification.urgency(match urgency {
+            Urgency::Low => notify_rust::Urgency::Low,
+            Urgency::Normal => notify_rust::Urgency::Normal,
+            Urgency::Critical => notify_rust::Urgency::Critical,
+        });
     }
 
-    None
+    notification
 }
 
-fn main() {
-    let opts = Opts::parse();
+/// The notification listing everything happening on `date`.
+fn daily_summary(config: &Config, date: NaiveDate) -> Notification {
+    let events = config.events_on(date);
+    let body = if events.is_empty() {
+        "no events today".to_string()
+    } else {
+        events
+            .iter()
+            .map(|event| format!("{} {}", event.time.format("%H:%M"), event.event))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    let mut notification = Notification::new();
+    notification
+        .summary(&format!("{} - ClassJoiner", Day::from(date.weekday())))
+        .body(&body);
+    notification
+}
+
+/// The heads-up notification sent `notify_before` an event with `pre_alert`, `until` being
+/// how long until it starts.
+fn pre_alert_notification(event: &Event, until: StdDuration) -> Notification {
+    let mut notification = Notification::new();
+    notification
+        .summary(&format!("{} - ClassJoiner", event.event))
+        .body(&format!(
+            "{} starts in {}, at {}",
+            event.event,
+            format_rounded(until, ConfigDuration(Duration::minutes(1))),
+            event.time.format("%H:%M")
+        ));
+    notification
+}
+
+/// The critical notification sent when `command` couldn't be launched for `event`.
+fn launch_failed(event: &Event, command: &CommandArgs, e: &io::Error) -> Notification {
+    let mut notification = Notification::new();
+    notification
+        .summary(&format!("{} - ClassJoiner", event.event))
+        .body(&format!("unable to launch {}: {}", command.name, e))
+        .urgency(notify_rust::Urgency::Critical);
+    notification
+}
+
+/// The critical notification sent when reloading the config failed with `e`.
+fn reload_failed(e: &AppError) -> Notification {
+    let mut notification = Notification::new();
+    notification
+        .summary("config reload failed - ClassJoiner")
+        .body(&format!("{}\n\nthe previous config is still in effect", e))
+        .urgency(notify_rust::Urgency::Critical);
+    notification
+}
+
+/// Launch `commands` for the occurrence of `event` starting at `start` along with their
+/// hooks, retrying those with `retries`, or with `no_run` only log what would be launched.
+/// Failures are logged, and with `notify` also shown as a
+/// notification. Returns whether anything was launched.
+fn launch_event(
+    config: &Config,
+    event: &Event,
+    commands: &[&CommandArgs],
+    start: DateTime<Zone>,
+    no_run: bool,
+    notify: bool,
+) -> bool {
+    if no_run {
+        // only say what we'd do, in real time so the schedule can be watched.
+        for command in commands {
+            let command = command.expand(Some(&event.event), start);
+            info!(
+                "would launch {} for event {} at {}",
+                command.to_string().trim_end(),
+                event.event,
+                start
+            );
+        }
+        return false;
+    }
+
+    let mut any_launched = false;
+    for command in commands {
+        let command = &command.expand(Some(&event.event), start);
+        match launch_chain(command, |command| {
+            spawn_retrying(config, command, CommandArgs::respawn)
+        }) {
+            Ok(()) => {
+                info!("launched {} for event {}", command.name, event.event);
+                any_launched = true;
+            }
+            Err(e) => {
+                error!("unable to launch {}: {}", command.name, e);
+                if notify {
+                    if let Err(e) = launch_failed(event, command, &e).show() {
+                        error!("unable to show notification: {}", e);
+                    }
+                }
+            }
+        }
+    }
+    any_launched
+}
+
+/// Something the daemon still has to do for an event after notifying for it.
+enum Step {
+    /// Launch a command, for a `launch_offset` that comes after the notification or for
+    /// retries that may take a while.
+    Launch(Box<CommandArgs>),
+    /// Notify of the launch, for `pre_alert`.
+    Notify,
+}
+
+/// Carry out `steps` for the occurrence of `event` starting at `start`, each at its time,
+/// from a thread of its own. The occurrence is recorded in the state file at `state_path`
+/// once that's done, if anything was launched then or `already_launched`. `notify` is as
+/// for launch_event. Shutting down stops it early and leaves the occurrence unrecorded, so a
+/// restarted daemon launches it.
+#[allow(clippy::too_many_arguments)]
+fn launch_later(
+    config: &Config,
+    event: &Event,
+    commands: &[&CommandArgs],
+    mut steps: Vec<(DateTime<Zone>, Step)>,
+    start: DateTime<Zone>,
+    already_launched: bool,
+    no_run: bool,
+    notify: bool,
+    state_path: Option<String>,
+) -> thread::JoinHandle<()> {
+    let (config, event) = (config.clone(), event.clone());
+    let commands: Vec<CommandArgs> = commands.iter().map(|&command| command.clone()).collect();
+    // a notification at the same time as launches comes after them, sorting keeps the order.
+    steps.sort_by_key(|(at, _)| *at);
+    thread::spawn(move || {
+        let mut launched = already_launched;
+        for (at, step) in steps {
+            if !sleep_through_reloads(at) {
+                return;
+            }
+            match step {
+                Step::Launch(command) => {
+                    launched |= launch_event(&config, &event, &[&command], start, no_run, notify);
+                }
+                Step::Notify => {
+                    let commands: Vec<_> = commands.iter().collect();
+                    notify_event(&config, &event, &commands, start, false, true, no_run);
+                }
+            }
+        }
+        if launched {
+            record_launch(state_path.as_deref(), &event.event, start);
+        }
+    })
+}
+
+/// How long "Snooze" on a notification puts off the event.
+const SNOOZE: StdDuration = StdDuration::from_secs(5 * 60);
+
+/// Show the notification for `event`, with buttons if the notification server supports
+/// them. If its commands were `launched` these are "Join now", which only dismisses it, and
+/// "Snooze 5m", which launches them again after `SNOOZE`. For a reminder they are "Open",
+/// which launches them, and "Snooze 5m", which reminds again after `SNOOZE`. The answer is
+/// waited for on a thread of its own.
+fn notify_event(
+    config: &Config,
+    event: &Event,
+    commands: &[&CommandArgs],
+    start: DateTime<Zone>,
+    late: bool,
+    launched: bool,
+    no_run: bool,
+) {
+    let mut notification = notification(event, commands, late, launched);
+    // servers without actions would show the buttons as text, or not at all.
+    let actions = notify_rust::get_capabilities()
+        .is_ok_and(|capabilities| capabilities.iter().any(|c| c == "actions"));
+    if actions {
+        if launched {
+            notification.action("join", "Join now");
+        } else {
+            notification.action("open", "Open");
+        }
+        notification.action("snooze", "Snooze 5m");
+    }
+
+    // a missing notification daemon shouldn't take the scheduler down with it.
+    let handle = match notification.show() {
+        Ok(handle) => handle,
+        Err(e) => {
+            error!("unable to show notification: {}", e);
+            return;
+        }
+    };
+    info!("sent notification for event {}", event.event);
+    if !actions {
+        return;
+    }
+
+    let (config, event) = (config.clone(), event.clone());
+    let commands: Vec<CommandArgs> = commands.iter().map(|&command| command.clone()).collect();
+    thread::spawn(move || {
+        handle.wait_for_action(|action| {
+            let commands: Vec<_> = commands.iter().collect();
+            if action == "open" {
+                launch_event(&config, &event, &commands, start, no_run, true);
+                return;
+            }
+            if action != "snooze" {
+                return;
+            }
+            info!("snoozed event {} for {:?}", event.event, SNOOZE);
+            if !sleep_through_reloads(config.now() + Duration::from_std(SNOOZE).unwrap()) {
+                return;
+            }
+
+            if launched {
+                launch_event(&config, &event, &commands, start, no_run, true);
+            }
+            notify_event(&config, &event, &commands, start, true, launched, no_run);
+        })
+    });
+}
+
+/// Spawn `command` with `spawn`, trying again up to its `retries` times and doubling the
+/// delay between attempts. Only gives up early when the daemon shuts down meanwhile, so it's
+/// meant for a launch thread rather than the daemon's own. `log_output` gets a single header
+/// however many attempts it takes, so `spawn` shouldn't write one.
+fn spawn_retrying(
+    config: &Config,
+    command: &CommandArgs,
+    mut spawn: impl FnMut(&CommandArgs) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut delay = command
+        .retry_delay
+        .map_or_else(|| Duration::seconds(5), |delay| delay.0);
+    let mut attempt = 0;
+    command.log_header()?;
+    loop {
+        match spawn(command) {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt < command.retries => {
+                attempt += 1;
+                warn!(
+                    "unable to launch {}: {}, retrying in {}s ({}/{})",
+                    command.name,
+                    e,
+                    delay.num_seconds(),
+                    attempt,
+                    command.retries
+                );
+                if !sleep_through_reloads(config.now() + delay) {
+                    return Err(e);
+                }
+                delay = delay * 2;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Run `command`'s `before` hook to completion, then `spawn` the command itself and then
+/// its `after` hook. A failing `before` hook aborts the chain so the command isn't
+/// launched, a failing `after` hook is only logged since the command is already running.
+fn launch_chain(
+    command: &CommandArgs,
+    spawn: impl FnOnce(&CommandArgs) -> io::Result<()>,
+) -> io::Result<()> {
+    if command.singleton && command.is_running() {
+        info!(
+            "{} is already running, not launching it again",
+            command.name
+        );
+        return Ok(());
+    }
+
+    if let Some(before) = &command.before {
+        before
+            .run()
+            .map_err(|e| io::Error::new(e.kind(), format!("before hook failed: {}", e)))?;
+    }
+
+    // checked first, the command we spawn is running too.
+    let reuse = command.reuse_window && command.is_running();
+    spawn(command)?;
+    if reuse && command.focus_window() {
+        info!(
+            "{} was already running, brought its window to the front",
+            command.name
+        );
+    }
+
+    if let Some(after) = &command.after {
+        if let Err(e) = after.spawn() {
+            error!("unable to launch after hook {}: {}", after.name, e);
+        }
+    }
+    Ok(())
+}
+
+/// Record the occurrence of `event` starting at `start` as launched in the state file at
+/// `path`, if there is one.
+fn record_launch(path: Option<&str>, event: &str, start: DateTime<Zone>) {
+    if let Some(path) = path {
+        if let Err(e) = state::record(path, event, start) {
+            error!("unable to write state file {}: {}", path, e);
+        }
+    }
+}
+
+/// Threads with launches still ahead of them, waited for when the daemon exits so that
+/// nothing they were due to do is lost.
+#[derive(Default)]
+struct Pending(Vec<thread::JoinHandle<()>>);
+
+impl Pending {
+    fn push(&mut self, handle: thread::JoinHandle<()>) {
+        self.0.retain(|handle| !handle.is_finished());
+        self.0.push(handle);
+    }
+}
+
+impl Drop for Pending {
+    fn drop(&mut self) {
+        self.0.retain(|handle| !handle.is_finished());
+        if !self.0.is_empty() {
+            info!(
+                "waiting for {} pending launch(es) before exiting",
+                self.0.len()
+            );
+        }
+        for handle in self.0.drain(..) {
+            if handle.join().is_err() {
+                error!("a pending launch panicked");
+            }
+        }
+    }
+}
+
+/// Spawn each of `commands` along with their hooks, or only print them with `no_run`,
+/// after filling in their placeholders for `event` starting at `start`. With `wait` each
+/// command is run to completion in turn, stopping at the first that fails.
+fn launch(
+    commands: &[&CommandArgs],
+    event: Option<&str>,
+    start: DateTime<Zone>,
+    no_run: bool,
+    wait: bool,
+) -> Result<(), AppError> {
+    for command in commands {
+        let command = &command.expand(event, start);
+        if no_run {
+            if let Some(before) = &command.before {
+                print!("{}", before);
+            }
+            println!("{}", command);
+            if let Some(after) = &command.after {
+                print!("{}", after);
+            }
+        } else if wait {
+            let mut status = None;
+            launch_chain(command, |command| {
+                status = Some(command.status()?);
+                Ok(())
+            })
+            .map_err(|e| AppError::Launch(command.name.clone(), e))?;
+            if let Some(status) = status.filter(|status| !status.success()) {
+                return Err(AppError::Exited(command.name.clone(), status));
+            }
+        } else {
+            launch_chain(command, CommandArgs::spawn)
+                .map_err(|e| AppError::Launch(command.name.clone(), e))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn run(opts: Opts) -> Result<(), AppError> {
+    if opts.test_notify {
+        Notification::new()
+            .summary("eventjoiner test")
+            .body("if you see this, notifications work")
+            .show()
+            .map_err(AppError::Notify)?;
+        println!("notification sent");
+        return Ok(());
+    }
+
+    if let Some(SubCommand::Init { force }) = opts.subcommand {
+        // a new config goes where it's meant to live, not wherever one was found.
+        let path = match &opts.config {
+            Some(path) => path.clone(),
+            None => default_config_path()?,
+        };
+        return write_template(&path, force);
+    }
 
     let config_path = match &opts.config {
         Some(path) => path.clone(),
-        None => format!(
-            "{}/eventjoiner.toml",
-            env::var("XDG_CONFIG_HOME").expect("$XDG_CONFIG_HOME not set, unable to read config")
-        ),
+        None => find_config_path()?,
     };
 
-    let config: Config =
-        toml::from_str(&fs::read_to_string(config_path).expect("unable to read config"))
-            .expect("unable to parse config");
+    if let Some(SubCommand::ConfigPath) = opts.subcommand {
+        if config_path == "-" {
+            println!("-");
+            return Ok(());
+        }
+        let path = std::path::absolute(&config_path)
+            .map_err(|e| AppError::ReadConfig(config_path.clone(), e))?;
+        println!("{}", path.display());
+        if !path.is_file() {
+            return Err(AppError::ReadConfig(
+                config_path,
+                io::Error::new(io::ErrorKind::NotFound, "no such file"),
+            ));
+        }
+        return Ok(());
+    }
 
-    if let Some(command) = opts.show_command {
-        let command = config
-            .command
-            .get(&command)
-            .expect(&format!("invalid command {}", command));
+    if let Some(SubCommand::InstallSystemd { force }) = opts.subcommand {
+        return install_systemd(&config_path, force);
+    }
 
-        println!("{}", command);
+    if let Some(SubCommand::UninstallSystemd) = opts.subcommand {
+        return uninstall_systemd();
+    }
 
-        return;
+    if let Some(SubCommand::Completions { shell }) = opts.subcommand {
+        print!("{}", completions::generate(shell, &Opts::into_app()));
+        return Ok(());
     }
 
-    if let Some(command) = &opts.launch {
-        let command = config
-            .command
-            .get(command)
-            .expect(&format!("invalid command {}", command));
+    if let Some(SubCommand::ImportIcal { file }) = &opts.subcommand {
+        let contents = fs::read_to_string(file).map_err(|e| AppError::ReadIcal(file.clone(), e))?;
+        let import = ical::Import::from_ical(&contents).map_err(AppError::ParseIcal)?;
+        print!(
+            "{}",
+            import
+                .to_toml()
+                .map_err(|e| AppError::ParseIcal(e.to_string()))?
+        );
+        return Ok(());
+    }
 
-        if opts.no_run {
-            println!("{}", command);
+    let state_path = opts.state_file.clone().or_else(state::default_path);
+
+    if opts.reset_state {
+        let path = state_path.ok_or(AppError::NoStatePath)?;
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                return Err(AppError::State(path, e));
+            }
+            _ => println!("cleared state file {}", path),
+        }
+        return Ok(());
+    }
+
+    let socket_path = opts.socket.clone().unwrap_or_else(status::default_path);
+
+    if let Some(SubCommand::Status) = opts.subcommand {
+        match status::query(&socket_path).map_err(|e| AppError::Status(socket_path, e))? {
+            Some(reply) if opts.json => print_json(&StatusOutput {
+                event: &reply.event,
+                start: &reply.start,
+                seconds_until: reply.seconds_until,
+            }),
+            Some(reply) => println!(
+                "waiting for {} at {} (in {})",
+                reply.event,
+                reply.start,
+                format_countdown(StdDuration::from_secs(reply.seconds_until))
+            ),
+            None if opts.json => println!("null"),
+            None => println!("not waiting for any event"),
+        }
+        return Ok(());
+    }
+
+    let mut config = match read_config(&config_path, opts.profile.as_deref()) {
+        // a pipeline gating on the report wants one even for a config that doesn't load.
+        Err(e) if opts.json && matches!(opts.subcommand, Some(SubCommand::Validate)) => {
+            return report_problems(&[load_problem(&e)], true, opts.strict);
+        }
+        config => config?,
+    };
+    if let Some(at) = opts.at {
+        config.clock = Some(config.at(at.date(), at.time()));
+    }
+
+    if let Some(SubCommand::Names { kinds }) = &opts.subcommand {
+        for kind in kinds {
+            let mut names: Vec<&String> = match kind {
+                NameKind::Commands => config.command.keys().collect(),
+                NameKind::Events => config.events.keys().collect(),
+                NameKind::Profiles => config.profiles.keys().collect(),
+            };
+            names.sort();
+            for name in names {
+                println!("{}", name);
+            }
+        }
+        return Ok(());
+    }
+
+    if let Some(SubCommand::ExportIcal) = opts.subcommand {
+        print!("{}", ical::export(&config));
+        return Ok(());
+    }
+
+    if let Some(SubCommand::Validate) = opts.subcommand {
+        let mut problems = config.check();
+        problems.extend(config.missing_binaries());
+        return report_problems(&problems, opts.json, opts.strict);
+    }
+
+    config.validate().map_err(AppError::InvalidConfig)?;
+
+    // overlaps are almost always a copy-paste mistake, the earlier event wins otherwise.
+    let overlaps = config.overlaps();
+    for overlap in &overlaps {
+        warn!("{}", overlap);
+    }
+    if opts.strict && !overlaps.is_empty() {
+        return Err(AppError::InvalidConfig(format!(
+            "{} overlapping event(s)",
+            overlaps.len()
+        )));
+    }
+
+    if let Some(SubCommand::NextLaunchTime) = opts.subcommand {
+        let at = next_launch_time(&config, config.now(), state_path.as_deref())
+            .ok_or(AppError::NoSchedule)?;
+        println!("{}", at.to_rfc3339_opts(SecondsFormat::Secs, false));
+        return Ok(());
+    }
+
+    if let Some(name) = opts.show_command {
+        if let Some(command) = config.command.get(&name) {
+            if opts.json {
+                print_json(command);
+            } else {
+                println!("{}", paint(Style::Command, command));
+            }
+        } else if config.event_mapping(&name).is_some() || config.is_scheduled(&name) {
+            let commands = resolve_event(&config, &name)?;
+            if opts.json {
+                print_json(&commands);
+            } else {
+                for command in commands {
+                    println!("{}", paint(Style::Command, command));
+                }
+            }
         } else {
-            Command::new(&command.name)
-                .args(&command.args)
-                .spawn()
-                .unwrap();
+            return Err(AppError::UnknownCommand(name));
         }
 
-        return;
+        return Ok(());
     }
 
-    if let Some(class) = &opts.event {
-        let command = config
-            .command
-            .get(
-                config
-                    .events
-                    .get(class)
-                    .expect(&format!("invalid class {}", class)),
-            )
-            .expect(&format!("class {} has no command", class));
+    if let Some(name) = &opts.show_event {
+        let commands = resolve_event(&config, name)?;
+        if opts.json {
+            print_json(&commands);
+        } else {
+            for command in commands {
+                println!("{}", paint(Style::Command, command));
+            }
+        }
 
-        if opts.no_run {
-            println!("{}", command);
+        return Ok(());
+    }
+
+    // better to say so than print an empty day, machine readable output stays as is.
+    let human = !opts.json && opts.format.is_none();
+    if (opts.list || opts.list_all || opts.next) && human && !config.has_events() {
+        println!("no events configured");
+        return Ok(());
+    }
+
+    if opts.remaining {
+        let now = config.now();
+        let today = now.date().naive_local();
+        // events_on is sorted by time, so the ones yet to start are all at the end.
+        let events = config.events_on(today);
+        let started = events.partition_point(|event| config.at(today, event.time) <= now);
+        let left = &events[started..];
+
+        if opts.json {
+            print_json(&RemainingOutput {
+                remaining: left.len(),
+                next: left.first().map(|event| event.event.as_str()),
+            });
         } else {
-            Command::new(&command.name)
-                .args(&command.args)
-                .spawn()
-                .unwrap();
+            println!("{}", left.len());
         }
+        return Ok(());
+    }
 
-        return;
+    if opts.list {
+        let now = config.now();
+        let date = query_date(&config, opts.day);
+        let day = Day::from(date.weekday());
+        let entries = list_entries(
+            &config,
+            day,
+            config.events_on(date),
+            // only today has events that are already over.
+            (date == now.date().naive_local()).then(|| now.time()),
+        );
+
+        if let Some(template) = &opts.format {
+            for event in config.events_on(date) {
+                println!(
+                    "{}",
+                    template.render(&format_row(&config, event, day, Some(date)))
+                );
+            }
+        } else if opts.json {
+            print_json(&entries);
+        } else {
+            println!("{}", paint(Style::Heading, day));
+            print_entries(&entries);
+        }
+
+        return Ok(());
+    }
+
+    if opts.list_all {
+        let mut all = Vec::new();
+        for day in Day::week_from(config.week_starts_on) {
+            let mut events = config.weekly_events(day);
+            events.retain(|event| event.enabled || opts.show_disabled);
+            if let Some(template) = &opts.format {
+                for event in events {
+                    println!(
+                        "{}",
+                        template.render(&format_row(&config, event, day, None))
+                    );
+                }
+                continue;
+            }
+            let entries = list_entries(&config, day, events, None);
+            if !opts.json && !entries.is_empty() {
+                println!("{}", paint(Style::Heading, day));
+                print_entries(&entries);
+            }
+            all.extend(entries);
+        }
+
+        if opts.json {
+            print_json(&all);
+        }
+
+        return Ok(());
+    }
+
+    if opts.next {
+        let countdown = |duration| match opts.round {
+            Some(unit) => format_rounded(duration, unit),
+            None => format_countdown(duration),
+        };
+        let now = config.now();
+        let date = query_date(&config, opts.day);
+        // look from just before the start of that day, unless it's today.
+        let from = if date == now.date().naive_local() {
+            now
+        } else {
+            config.at(date, NaiveTime::from_hms(0, 0, 0)) - Duration::seconds(1)
+        };
+
+        if let Some(count) = opts.count {
+            let upcoming: Vec<_> = upcoming_at(&config, from)
+                .take_while(|(start, _)| opts.day.is_none() || start.date().naive_local() == date)
+                .take(count)
+                .collect();
+
+            if let Some(template) = &opts.format {
+                for (start, event) in upcoming {
+                    let date = start.date().naive_local();
+                    let row = format_row(&config, event, Day::from(date.weekday()), Some(date));
+                    println!("{}", template.render(&row));
+                }
+            } else if opts.json {
+                let output: Vec<_> = upcoming
+                    .iter()
+                    .map(|(start, event)| NextOutput {
+                        event: &event.event,
+                        start: start.to_rfc3339_opts(SecondsFormat::Secs, false),
+                        seconds_until: (*start - now).to_std().unwrap_or_default().as_secs(),
+                        commands: config.commands_for(event),
+                    })
+                    .collect();
+                print_json(&output);
+            } else if upcoming.is_empty() {
+                println!("no upcoming events");
+            } else {
+                for (start, event) in upcoming {
+                    println!(
+                        "{} on {} at {} (in {})",
+                        paint(Style::Event, &event.event),
+                        start.format("%a %Y-%m-%d"),
+                        paint(Style::Time, event.time),
+                        countdown((start - now).to_std().unwrap_or_default())
+                    );
+                    for command in config.commands_for(event) {
+                        print!("{}", paint(Style::Command, command));
+                    }
+                }
+            }
+
+            return Ok(());
+        }
+
+        let next = next_class_at(&config, from)
+            .map(|(duration, commands, event)| {
+                let start = event_start(&config, from, duration, &event);
+                (start, commands, event)
+            })
+            .filter(|(start, ..)| opts.day.is_none() || start.date().naive_local() == date);
+        match next {
+            Some((start, commands, event)) => {
+                let until_start = (start - now).to_std().unwrap_or_default();
+
+                if let Some(template) = &opts.format {
+                    let date = start.date().naive_local();
+                    let row = format_row(&config, &event, Day::from(date.weekday()), Some(date));
+                    println!("{}", template.render(&row));
+                } else if opts.json {
+                    print_json(&NextOutput {
+                        event: &event.event,
+                        start: start.to_rfc3339_opts(SecondsFormat::Secs, false),
+                        seconds_until: until_start.as_secs(),
+                        commands,
+                    });
+                } else {
+                    println!(
+                        "{} at {} (in {})",
+                        paint(Style::Event, &event.event),
+                        paint(Style::Time, event.time),
+                        countdown(until_start)
+                    );
+                    for command in commands {
+                        print!("{}", paint(Style::Command, command));
+                    }
+                }
+            }
+            None if opts.json => println!("null"),
+            None => println!("no upcoming events"),
+        }
+
+        return Ok(());
     }
 
-    if opts.deamonize {
-        let notify_duration = Duration::minutes(config.notify_before as i64 + 1)
-            .to_std()
-            .unwrap();
+    if opts.watch {
+        install_signal_handler(libc::SIGTERM, on_shutdown);
+        install_signal_handler(libc::SIGINT, on_shutdown);
+        let countdown = |duration| match opts.round {
+            Some(unit) => format_rounded(duration, unit),
+            None => format_clock(duration),
+        };
+
+        // "\r\x1b[2K" takes the cursor back and clears the line, so each redraw replaces
+        // the last one.
+        let mut waiting: Option<(Event, DateTime<Zone>)> = None;
         loop {
-            // get sleep duration and command
-            let (duration, command, schedule) = next_class(&config).expect("no schedule set");
+            let now = config.now();
+            if let Some((event, start)) = waiting.take().filter(|(_, start)| *start < now) {
+                println!(
+                    "\r\x1b[2K{} launched at {}",
+                    event.event,
+                    start.format("%H:%M")
+                );
+            }
 
-            println!("sleeping for {:?}", duration);
+            match next_class_at(&config, now) {
+                Some((duration, commands, event)) => {
+                    let start = event_start(&config, now, duration, &event);
+                    let commands: Vec<_> = commands
+                        .iter()
+                        .map(|command| {
+                            let command = command.expand(Some(&event.event), start);
+                            command.to_string().trim_end().to_string()
+                        })
+                        .collect();
+                    print!(
+                        "\r\x1b[2K{} at {} in {} - {}",
+                        event.event,
+                        start.format("%a %H:%M"),
+                        countdown((start - now).to_std().unwrap_or_default()),
+                        commands.join(", ")
+                    );
+                    waiting = Some((event, start));
+                }
+                None => print!("\r\x1b[2Kno upcoming events"),
+            }
+            let _ = io::stdout().flush();
 
-            // sleep until 5 minutes before event time comes around
-            thread::sleep(duration);
+            if !sleep_until(now + Duration::seconds(1)) {
+                println!();
+                return Ok(());
+            }
+        }
+    }
 
-            // launch the command
-            let _ = Command::new(&command.name).args(&command.args).spawn();
+    if let Some(until) = &opts.until {
+        let now = config.now();
+        let (duration, commands, event) = match next_class_at(&config, now) {
+            Some(next) => next,
+            None => return Ok(()),
+        };
+        let start = event_start(&config, now, duration, &event);
+        if start - now > until.0 {
+            debug!("next event {} at {} isn't due yet", event.event, start);
+            return Ok(());
+        }
 
-            // also launch a notification to let user know
-            Notification::new()
-                .summary(&format!("{} - ClassJoiner", schedule.event))
-                .body("class launched")
-                .timeout(Timeout::Milliseconds(6000))
+        let path = state_path.ok_or(AppError::NoStatePath)?;
+        if state::launched(&path, &event.event, start) {
+            debug!("already launched event {} at {}", event.event, start);
+            return Ok(());
+        }
+        if config.auto_launch(&event) {
+            launch(&commands, Some(&event.event), start, opts.no_run, false)?;
+        } else if config.notifications && !opts.quiet && !opts.no_run {
+            // nobody is around to press a button once we've exited.
+            notification(&event, &commands, false, false)
                 .show()
-                .unwrap();
+                .map_err(AppError::Notify)?;
+        } else {
+            println!("not launching {}, auto_launch is off", event.event);
+        }
+        if !opts.no_run {
+            state::record(&path, &event.event, start).map_err(|e| AppError::State(path, e))?;
+        }
+        return Ok(());
+    }
+
+    if let Some(command) = &opts.launch {
+        let command = config
+            .command
+            .get(command)
+            .ok_or_else(|| AppError::UnknownCommand(command.clone()))?;
+
+        // there's no event to speak of, only when it's launched.
+        return launch(&[command], None, config.now(), opts.no_run, opts.wait);
+    }
+
+    if let Some(class) = &opts.event {
+        return launch(
+            &resolve_event(&config, class)?,
+            Some(class),
+            config.now(),
+            opts.no_run,
+            opts.wait,
+        );
+    }
+
+    if opts.launch_next {
+        let now = config.now();
+        let (start, event) = match live_event_at(&config, now) {
+            Some((start, event)) => (start, event.clone()),
+            None => match next_class_at(&config, now) {
+                Some((duration, _, event)) => (event_start(&config, now, duration, &event), event),
+                None => {
+                    println!("no upcoming events");
+                    return Ok(());
+                }
+            },
+        };
+
+        let verb = if start <= now { "started" } else { "starts" };
+        println!("{} ({} at {})", event.event, verb, event.time);
+        launch(
+            &config.commands_for(&event),
+            Some(&event.event),
+            start,
+            opts.no_run,
+            false,
+        )?;
 
-            // sleep until next event starts, and then check for more later.
-            thread::sleep(notify_duration);
+        // so the daemon or --until doesn't launch it all over again.
+        if let Some(path) = state_path.as_deref().filter(|_| !opts.no_run) {
+            if let Err(e) = state::record(path, &event.event, start) {
+                error!("unable to write state file {}: {}", path, e);
+            }
         }
+        return Ok(());
     }
 
-    match get_event_and_command(&config) {
-        Some((schedule, command)) => {
-            println!("class = {}", schedule.event);
+    if opts.daemonize {
+        if !config.has_events() {
+            return Err(AppError::NoEvents);
+        }
+        let _pidfile = opts.pidfile.as_deref().map(PidFile::create).transpose()?;
+
+        // being unobservable is no reason not to launch anything.
+        let state = status::State::default();
+        let _server = match status::Server::bind(&socket_path, state.clone()) {
+            Ok(server) => Some(server),
+            Err(e) => {
+                error!("unable to listen on {}: {}", socket_path, e);
+                None
+            }
+        };
+
+        // better to hear about a typo now than when the event comes around.
+        for missing in config.missing_binaries() {
+            warn!("{}", missing);
+        }
+
+        install_signal_handler(libc::SIGHUP, on_sighup);
+        install_signal_handler(libc::SIGTERM, on_shutdown);
+        install_signal_handler(libc::SIGINT, on_shutdown);
+
+        let mut config = config;
+        // dropped before the PID file, so the daemon still looks alive while it waits.
+        let mut pending = Pending::default();
+        // the next event has to start from here on, so one we just handled isn't handled
+        // again and one that starts while it's on is skipped.
+        let mut next_from: Option<DateTime<Zone>> = None;
+        // when we last launched something, for min_gap.
+        let mut last_launch: Option<DateTime<Zone>> = None;
+        // the day we last sent a summary for, in case there's no state file to remember it.
+        let mut last_summary: Option<NaiveDate> = None;
+        loop {
+            // anything we already launched is left running.
+            if SHUTDOWN.load(Ordering::SeqCst) {
+                info!("shutting down");
+                return Ok(());
+            }
+
+            let reload = RELOAD.swap(false, Ordering::SeqCst);
+            if reload && config_path == "-" {
+                warn!("unable to reload a config read from stdin, keeping the old one");
+            } else if reload {
+                match read_config(&config_path, opts.profile.as_deref()).and_then(|new| {
+                    new.validate().map_err(AppError::InvalidConfig)?;
+                    Ok(new)
+                }) {
+                    Ok(new) => {
+                        config = new;
+                        info!("reloaded config from {}", config_path);
+                    }
+                    Err(e) => {
+                        error!("unable to reload config, keeping the old one: {}", e);
+                        if config.notifications && !opts.quiet {
+                            if let Err(e) = reload_failed(&e).show() {
+                                error!("unable to show notification: {}", e);
+                            }
+                        }
+                    }
+                }
+            }
 
-            if opts.no_run {
-                println!("{}", command);
+            // get sleep duration and commands
+            let now = config.now();
+            let today = now.date().naive_local();
+            let summaries = config.daily_summary && config.notifications && !opts.quiet;
+            let summarized = last_summary == Some(today)
+                || state_path
+                    .as_deref()
+                    .is_some_and(|path| state::summarized(path, today));
+            if summaries && !summarized {
+                last_summary = Some(today);
+                match daily_summary(&config, today).show() {
+                    Ok(_) => info!("sent summary for {}", today),
+                    Err(e) => error!("unable to show notification: {}", e),
+                }
+                if let Some(path) = &state_path {
+                    if let Err(e) = state::record_summary(path, today) {
+                        error!("unable to write state file {}: {}", path, e);
+                    }
+                }
+            }
+
+            let from = next_from.filter(|from| *from > now).unwrap_or(now);
+            let (duration, commands, schedule) = match next_class_at(&config, from) {
+                Some(next) => next,
+                None => {
+                    let today = now.date().naive_local();
+                    match (config.valid_from, config.valid_until) {
+                        (_, Some(until)) if until < today => {
+                            info!("timetable ended on {}, nothing left to do", until);
+                            return Ok(());
+                        }
+                        (Some(from), _) if today < from && opts.exit_when_idle.is_some() => {
+                            info!("timetable starts on {}, nothing to do until then", from);
+                            return Ok(());
+                        }
+                        (Some(from), _) if today < from => {
+                            info!("timetable starts on {}, waiting until then", from);
+                            sleep_until(config.at(from, NaiveTime::from_hms(0, 0, 0)));
+                            continue;
+                        }
+                        // e.g. reloaded with every event taken out.
+                        _ if !config.has_events() => return Err(AppError::NoEvents),
+                        _ => return Err(AppError::NoSchedule),
+                    }
+                }
+            };
+
+            let start = event_start(&config, from, duration, &schedule);
+            let idle = match opts.exit_when_idle {
+                Some(IdleAfter::Day) => start.date().naive_local() != today,
+                Some(IdleAfter::Week) => {
+                    config.week_start(start.date().naive_local()) != config.week_start(today)
+                }
+                None => false,
+            };
+            if idle {
+                info!(
+                    "next event {} is on {}, nothing left to do until then",
+                    schedule.event,
+                    start.date().naive_local()
+                );
+                return Ok(());
+            }
+            let mut notify_at = start - config.notify_before(&schedule);
+            if let (Some(last), Some(gap)) = (last_launch, config.min_gap) {
+                // no sooner than min_gap after the last one, but no later than the start.
+                notify_at = notify_at.max(last + gap.0).min(start);
+            }
+
+            debug!("next event {} starts at {}", schedule.event, start);
+            *state.lock().unwrap() = Some(status::Waiting {
+                event: schedule.event.clone(),
+                start,
+            });
+            info!(
+                "sleeping for {:?}, until {} for event {}",
+                (notify_at - now).to_std().unwrap_or_default(),
+                local_time(notify_at),
+                schedule.event
+            );
+
+            // sleep until 5 minutes before event time comes around, or until the next day
+            // starts and wants its summary. If we're woken up to reload or shut down, start
+            // over so that's handled first.
+            let wake = if summaries {
+                notify_at.min(config.at(today.succ(), NaiveTime::from_hms(0, 0, 0)))
             } else {
-                Command::new(&command.name)
-                    .args(&command.args)
-                    .spawn()
-                    .unwrap();
+                notify_at
+            };
+            if !sleep_until(wake) {
+                continue;
+            }
+            // how far past the deadline we woke up shows drift, e.g. from a suspend.
+            let woke = config.now();
+            info!(
+                "woke up at {}, {}s after {}",
+                local_time(woke),
+                (woke - wake).num_seconds(),
+                local_time(wake)
+            );
+            if woke < notify_at {
+                continue;
             }
+
+            // if the machine was suspended we may have woken up well past the deadline,
+            // after the event already started or even ended.
+            *state.lock().unwrap() = None;
+            let now = config.now();
+            let late = now > start;
+            let deadline = config
+                .end_at(start.date().naive_local(), &schedule)
+                .unwrap_or_else(|| start + opts.catchup_grace.0);
+            if now > deadline {
+                warn!("missed event {} at {}", schedule.event, start);
+                continue;
+            }
+
+            let launched = state_path
+                .as_deref()
+                .is_some_and(|path| state::launched(path, &schedule.event, start));
+            if launched {
+                // e.g. we were restarted, or cron got to it first.
+                info!("already launched event {} at {}", schedule.event, start);
+            } else {
+                last_launch = Some(now);
+                let notify = config.notifications && !opts.quiet;
+                let auto_launch = config.auto_launch(&schedule);
+                let pre_alert = notify && auto_launch && config.pre_alert(&schedule) && start > now;
+                let mut later = Vec::new();
+                let any_launched = if auto_launch {
+                    // commands with a launch_offset still ahead wait for it, and those that
+                    // may have to be retried are launched from the thread that waits too.
+                    let (deferred, due): (Vec<_>, Vec<_>) = commands
+                        .iter()
+                        .map(|&command| (command, config.launch_at(&schedule, command, start)))
+                        .partition(|(command, at)| {
+                            at.is_some_and(|at| at > now) || command.retries > 0
+                        });
+                    for (command, at) in deferred {
+                        let at = at.filter(|at| *at > now).unwrap_or(now);
+                        if at > now {
+                            info!(
+                                "will launch {} for event {} at {}",
+                                command.name,
+                                schedule.event,
+                                local_time(at)
+                            );
+                        }
+                        later.push((at, Step::Launch(Box::new(command.clone()))));
+                    }
+                    let due: Vec<_> = due.into_iter().map(|(command, _)| command).collect();
+                    launch_event(&config, &schedule, &due, start, opts.no_run, notify)
+                } else {
+                    if !notify {
+                        warn!(
+                            "event {} has auto_launch off and notifications are off too",
+                            schedule.event
+                        );
+                    }
+                    info!("not launching event {}, auto_launch is off", schedule.event);
+                    false
+                };
+                if pre_alert {
+                    // the commands wait for the start, and so does telling of their launch.
+                    later.push((start, Step::Notify));
+                }
+
+                // a reminder counts as handled too, so a restart doesn't remind again.
+                let reminded = !auto_launch && notify && !opts.no_run;
+                if !later.is_empty() {
+                    // recorded once the rest is launched too, a restart before then has to
+                    // launch it.
+                    pending.push(launch_later(
+                        &config,
+                        &schedule,
+                        &commands,
+                        later,
+                        start,
+                        any_launched,
+                        opts.no_run,
+                        notify,
+                        state_path.clone(),
+                    ));
+                } else if any_launched || reminded {
+                    record_launch(state_path.as_deref(), &schedule.event, start);
+                }
+
+                // also launch a notification to let user know, unless asked to stay quiet
+                if pre_alert {
+                    let until = (start - now).to_std().unwrap_or_default();
+                    if let Err(e) = pre_alert_notification(&schedule, until).show() {
+                        error!("unable to show notification: {}", e);
+                    }
+                } else if notify {
+                    notify_event(
+                        &config,
+                        &schedule,
+                        &commands,
+                        start,
+                        late,
+                        auto_launch,
+                        opts.no_run,
+                    );
+                }
+            }
+
+            // move on to the first event that starts once this one is over if we know when
+            // that is, otherwise to the first one that starts after it.
+            next_from = Some(
+                config
+                    .end_at(start.date().naive_local(), &schedule)
+                    .unwrap_or(start + Duration::seconds(1)),
+            );
+        }
+    }
+
+    match get_event_and_command(&config) {
+        Some((schedule, commands, started)) => {
+            if started {
+                println!("class = {} (started at {})", schedule.event, schedule.time);
+            } else {
+                println!("class = {}", schedule.event);
+            }
+
+            // an event that started later in the day than now is yesterday's, still
+            // running past midnight.
+            let now = config.now();
+            let today = now.date().naive_local();
+            let date = if started && schedule.time > now.time() {
+                today.pred()
+            } else {
+                today
+            };
+            let start = config.at(date, schedule.time);
+            launch(&commands, Some(&schedule.event), start, opts.no_run, false)?;
         }
         None => println!("no class"),
     }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    /// A config made of `toml`, which sets at least `notify_before`, with every event running
+    /// a command that does nothing.
+    fn config(toml: &str) -> Config {
+        let toml = format!(
+            "version = {}\ndefault_command = 'nothing'\n{}\n[command.nothing]\nname = 'true'\n",
+            CONFIG_VERSION, toml
+        );
+        Config::from_toml(&toml).unwrap()
+    }
+
+    /// `time` on `date`, written like "2024-01-07" and "23:50", in the config's zone.
+    fn at(config: &Config, date: &str, time: &str) -> DateTime<Zone> {
+        let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap();
+        config.at(date, NaiveTime::parse_from_str(time, "%H:%M").unwrap())
+    }
+
+    /// A file named `name` in the temp dir, unique to this test run and removed once dropped.
+    struct TempPath(PathBuf);
+
+    impl TempPath {
+        fn new(name: &str) -> Self {
+            TempPath(env::temp_dir().join(format!("eventjoiner-{}-{}", process::id(), name)))
+        }
+    }
+
+    impl Drop for TempPath {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn pid_file_with_a_group_pid_is_stale() {
+        for contents in ["0\n", "-1\n"] {
+            let path = TempPath::new("group.pid");
+            fs::write(&path.0, contents).unwrap();
+            let pid_file = PidFile::create(path.0.to_str().unwrap()).unwrap();
+            let written = fs::read_to_string(&path.0).unwrap();
+            assert_eq!(written.trim(), process::id().to_string());
+            drop(pid_file);
+        }
+    }
+
+    #[test]
+    fn exclusive_flags_conflict() {
+        let flags: [&[&str]; 5] = [
+            &["--launch", "zoom"],
+            &["--event", "lecture"],
+            &["--daemonize"],
+            &["--sc", "zoom"],
+            &["--show-event", "lecture"],
+        ];
+        for (i, a) in flags.iter().enumerate() {
+            for b in &flags[i + 1..] {
+                let args = ["eventjoiner"].iter().chain(*a).chain(*b);
+                let err = Opts::try_parse_from(args).unwrap_err();
+                assert_eq!(
+                    err.kind,
+                    clap::ErrorKind::ArgumentConflict,
+                    "{:?} {:?}",
+                    a,
+                    b
+                );
+            }
+        }
+
+        // each on its own is fine.
+        for flag in flags {
+            assert!(Opts::try_parse_from(["eventjoiner"].iter().chain(flag)).is_ok());
+        }
+    }
+
+    #[test]
+    fn failing_launch_is_retried_before_giving_up() {
+        let config = config("notify_before = 5\n[timetable]");
+        let event: Event = toml::from_str("time = '09:00:00'\nevent = 'lecture'").unwrap();
+        let command: CommandArgs =
+            toml::from_str("name = 'missing'\nretries = 3\nretry_delay = '0s'").unwrap();
+
+        let mut attempts = 0;
+        let result = spawn_retrying(&config, &command, |_| {
+            attempts += 1;
+            Err(io::Error::new(io::ErrorKind::NotFound, "not found"))
+        });
+        // the first attempt and all three retries, and only then the critical notification.
+        assert_eq!(attempts, 4);
+        let notification = launch_failed(&event, &command, &result.unwrap_err());
+        assert_eq!(notification.body, "unable to launch missing: not found");
+        assert!(notification
+            .hints
+            .contains(&notify_rust::Hint::Urgency(notify_rust::Urgency::Critical)));
+
+        // one that comes through on a retry is launched after all.
+        let mut attempts = 0;
+        let result = spawn_retrying(&config, &command, |_| {
+            attempts += 1;
+            match attempts {
+                1 | 2 => Err(io::Error::new(io::ErrorKind::NotFound, "not found")),
+                _ => Ok(()),
+            }
+        });
+        assert!(result.is_ok());
+        assert_eq!(attempts, 3);
+    }
+
+    #[test]
+    fn retries_share_one_log_header() {
+        let config = config("notify_before = 5\n[timetable]");
+        let log = TempPath::new("retries.log");
+        let command: CommandArgs = toml::from_str(&format!(
+            "name = '/nonexistent'\nretries = 2\nretry_delay = '0s'\nlog_output = '{}'",
+            log.0.display()
+        ))
+        .unwrap();
+
+        assert!(spawn_retrying(&config, &command, CommandArgs::respawn).is_err());
+        let contents = fs::read_to_string(&log.0).unwrap();
+        assert_eq!(contents.matches("==> ").count(), 1);
+    }
+
+    #[test]
+    fn start_across_midnight_inside_the_window() {
+        // 2024-01-07 is a Sunday.
+        let config = config(
+            "notify_before = 30
+            [timetable]
+            mon = [{ time = '00:05:00', event = 'late lecture' }]",
+        );
+        // the notification was due at 23:35 already.
+        let now = at(&config, "2024-01-07", "23:50");
+        let (duration, _, event) = next_class_at(&config, now).unwrap();
+        assert_eq!(event.event, "late lecture");
+        assert!(duration.is_zero());
+        assert_eq!(
+            event_start(&config, now, duration, &event),
+            at(&config, "2024-01-08", "00:05")
+        );
+
+        // and before it was due, counting down to it.
+        let now = at(&config, "2024-01-07", "23:00");
+        let (duration, _, event) = next_class_at(&config, now).unwrap();
+        assert_eq!(duration, StdDuration::from_secs(35 * 60));
+        assert_eq!(
+            event_start(&config, now, duration, &event),
+            at(&config, "2024-01-08", "00:05")
+        );
+    }
+
+    #[test]
+    fn back_to_back_events_chain() {
+        // 2024-01-03 is a Wednesday.
+        let config = config(
+            "notify_before = 5
+            [timetable]
+            wed = [
+                { time = '09:00:00', event = 'first' },
+                { time = '09:10:00', event = 'second' },
+            ]",
+        );
+        let now = at(&config, "2024-01-03", "08:00");
+        let (duration, _, event) = next_class_at(&config, now).unwrap();
+        assert_eq!(event.event, "first");
+        let start = event_start(&config, now, duration, &event);
+        assert_eq!(start, at(&config, "2024-01-03", "09:00"));
+
+        // having handled it at 08:55, the daemon moves on from just after its start.
+        let from = start + Duration::seconds(1);
+        let (duration, _, event) = next_class_at(&config, from).unwrap();
+        assert_eq!(event.event, "second");
+        assert_eq!(
+            event_start(&config, from, duration, &event),
+            at(&config, "2024-01-03", "09:10")
+        );
+    }
+
+    #[test]
+    fn empty_timetable_through_each_entry_point() {
+        let path = TempPath::new("empty.toml");
+        let toml = format!(
+            "version = {}\nnotify_before = 5\n[timetable]\n",
+            CONFIG_VERSION
+        );
+        fs::write(&path.0, toml).unwrap();
+        let config = read_config(path.0.to_str().unwrap(), None).unwrap();
+        let now = at(&config, "2024-01-01", "09:00");
+
+        // nothing to launch, nothing to list and nothing running.
+        assert!(next_class_at(&config, now).is_none());
+        assert_eq!(upcoming_at(&config, now).count(), 0);
+        assert!(live_event_at(&config, now).is_none());
+        assert!(next_launch_time(&config, now, None).is_none());
+    }
+
+    #[test]
+    fn min_gap_holds_back_the_next_launch() {
+        let config = config(
+            "notify_before = 5
+            min_gap = 12
+            [timetable]
+            wed = [
+                { time = '09:00:00', event = 'first', auto_launch = false },
+                { time = '09:10:00', event = 'second' },
+            ]",
+        );
+        // woken for the first at 08:55, so the second waits until 09:07 rather than 09:05.
+        let now = at(&config, "2024-01-03", "08:00");
+        assert_eq!(
+            next_launch_time(&config, now, None),
+            Some(at(&config, "2024-01-03", "09:07"))
+        );
+    }
+
+    #[test]
+    fn pre_alert_launch_waits_for_the_start() {
+        let config = config(
+            "notify_before = 10
+            pre_alert = true
+            [timetable]
+            wed = [{ time = '10:00:00', event = 'lecture' }]",
+        );
+        // woken at 09:50 for the heads-up, the command itself is spawned at 10:00.
+        let now = at(&config, "2024-01-03", "09:00");
+        let (duration, _, event) = next_class_at(&config, now).unwrap();
+        assert_eq!(
+            now + Duration::from_std(duration).unwrap(),
+            at(&config, "2024-01-03", "09:50")
+        );
+        assert_eq!(
+            event_start(&config, now, duration, &event),
+            at(&config, "2024-01-03", "10:00")
+        );
+        assert_eq!(
+            next_launch_time(&config, now, None),
+            Some(at(&config, "2024-01-03", "10:00"))
+        );
+    }
 }
@@ -0,0 +1,93 @@
+//! The state file remembering the last launched occurrence of an event, so that the same
+//! one isn't launched twice across runs, and the last day the daemon sent its summary for.
+//!
+//! It holds a line per thing remembered, its kind and its fields separated by tabs:
+//! `launch`, the event's name and its start as an RFC 3339 datetime, and `summary` and the
+//! date. A line with only the event and its start is a launch written by older versions.
+
+use std::{env, fs, io, path::Path};
+
+use chrono::{DateTime, NaiveDate, SecondsFormat};
+use eventjoiner::tz::Zone;
+
+/// `$XDG_STATE_HOME/eventjoiner/last-launch`, or else under `$HOME/.local/state`.
+pub fn default_path() -> Option<String> {
+    let state_home = match env::var("XDG_STATE_HOME") {
+        Ok(dir) if !dir.is_empty() => dir,
+        _ => match env::var("HOME") {
+            Ok(home) if !home.is_empty() => format!("{}/.local/state", home),
+            _ => return None,
+        },
+    };
+
+    Some(format!("{}/eventjoiner/last-launch", state_home))
+}
+
+/// What the state file remembers.
+#[derive(Default)]
+struct State {
+    /// the event and start of the last launch, as written in the file.
+    launch: Option<String>,
+    summary: Option<NaiveDate>,
+}
+
+fn launch_line(event: &str, start: DateTime<Zone>) -> String {
+    format!(
+        "{}\t{}",
+        event,
+        start.to_rfc3339_opts(SecondsFormat::Secs, false)
+    )
+}
+
+/// A missing or unreadable state file remembers nothing.
+fn read(path: &str) -> State {
+    let mut state = State::default();
+    for line in fs::read_to_string(path).unwrap_or_default().lines() {
+        match line.split_once('\t') {
+            Some(("launch", launch)) => state.launch = Some(launch.to_string()),
+            Some(("summary", date)) => state.summary = date.parse().ok(),
+            Some(_) => state.launch = Some(line.to_string()),
+            None => {}
+        }
+    }
+    state
+}
+
+fn write(path: &str, state: &State) -> io::Result<()> {
+    if let Some(dir) = Path::new(path).parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut contents = String::new();
+    if let Some(launch) = &state.launch {
+        contents.push_str(&format!("launch\t{}\n", launch));
+    }
+    if let Some(date) = state.summary {
+        contents.push_str(&format!("summary\t{}\n", date));
+    }
+    fs::write(path, contents)
+}
+
+/// Whether the occurrence of `event` starting at `start` was the last one launched.
+pub fn launched(path: &str, event: &str, start: DateTime<Zone>) -> bool {
+    read(path).launch == Some(launch_line(event, start))
+}
+
+/// Remember the occurrence of `event` starting at `start` as the last one launched.
+pub fn record(path: &str, event: &str, start: DateTime<Zone>) -> io::Result<()> {
+    let mut state = read(path);
+    state.launch = Some(launch_line(event, start));
+    write(path, &state)
+}
+
+/// Whether the daily summary for `date` was sent already.
+pub fn summarized(path: &str, date: NaiveDate) -> bool {
+    read(path).summary == Some(date)
+}
+
+/// Remember that the daily summary for `date` was sent.
+pub fn record_summary(path: &str, date: NaiveDate) -> io::Result<()> {
+    let mut state = read(path);
+    state.summary = Some(date);
+    write(path, &state)
+}
@@ -0,0 +1,132 @@
+//! The daemon's control socket, which answers `eventjoiner status` with what it is waiting
+//! for.
+//!
+//! The protocol is a single line each way: the client sends `status`, and the daemon
+//! replies with the event, its start as an RFC 3339 datetime and the seconds until then,
+//! separated by tabs, or with `idle` when it isn't waiting for anything.
+
+use std::{
+    env,
+    io::{self, BufRead, BufReader, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use eventjoiner::tz::Zone;
+use log::{debug, warn};
+
+/// The socket the daemon listens on, under `$XDG_RUNTIME_DIR` or else in `/tmp`.
+pub fn default_path() -> String {
+    match env::var("XDG_RUNTIME_DIR") {
+        Ok(dir) if !dir.is_empty() => format!("{}/eventjoiner.sock", dir),
+        // SAFETY: getuid can't fail.
+        _ => format!("/tmp/eventjoiner-{}.sock", unsafe { libc::getuid() }),
+    }
+}
+
+/// The event the daemon is currently waiting for.
+#[derive(Debug, Clone)]
+pub struct Waiting {
+    pub event: String,
+    pub start: DateTime<Zone>,
+}
+
+/// What the daemon is up to, shared with the thread answering the socket.
+pub type State = Arc<Mutex<Option<Waiting>>>;
+
+/// A listening control socket, removed again when dropped.
+pub struct Server(String);
+
+impl Server {
+    /// Listen on `path` and answer requests from `state` in the background. A socket left
+    /// behind by a daemon that is gone is replaced, a live one is an error.
+    pub fn bind(path: &str, state: State) -> io::Result<Self> {
+        if UnixStream::connect(path).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                "another daemon is listening on it",
+            ));
+        }
+        let _ = std::fs::remove_file(path);
+
+        let listener = UnixListener::bind(path)?;
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream.and_then(|stream| answer(stream, &state)) {
+                    Ok(()) => debug!("answered status request"),
+                    Err(e) => warn!("unable to answer status request: {}", e),
+                }
+            }
+        });
+
+        Ok(Server(path.to_string()))
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+fn answer(stream: UnixStream, state: &State) -> io::Result<()> {
+    let mut request = String::new();
+    if BufReader::new(&stream).read_line(&mut request)? == 0 {
+        // someone only checking whether we're listening, like a daemon starting up.
+        return Ok(());
+    }
+
+    let reply = match request.trim() {
+        "status" => match &*state.lock().unwrap() {
+            Some(waiting) => format!(
+                "{}\t{}\t{}",
+                waiting.event,
+                waiting.start.to_rfc3339_opts(SecondsFormat::Secs, false),
+                waiting
+                    .start
+                    .signed_duration_since(Utc::now())
+                    .num_seconds()
+                    .max(0)
+            ),
+            None => "idle".to_string(),
+        },
+        other => format!("error unknown request {}", other),
+    };
+
+    writeln!(&stream, "{}", reply)
+}
+
+/// The daemon's answer to a `status` request.
+#[derive(Debug)]
+pub struct Reply {
+    pub event: String,
+    /// start of the event as an RFC 3339 datetime.
+    pub start: String,
+    pub seconds_until: u64,
+}
+
+/// Ask the daemon listening on `path` what it is waiting for, `None` if nothing.
+pub fn query(path: &str) -> io::Result<Option<Reply>> {
+    let mut stream = UnixStream::connect(path)?;
+    writeln!(stream, "status")?;
+
+    let mut reply = String::new();
+    BufReader::new(&stream).read_line(&mut reply)?;
+    let reply = reply.trim_end();
+    if reply == "idle" {
+        return Ok(None);
+    }
+
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("bad reply {}", reply));
+    let mut fields = reply.split('\t');
+    match (fields.next(), fields.next(), fields.next(), fields.next()) {
+        (Some(event), Some(start), Some(seconds), None) => Ok(Some(Reply {
+            event: event.to_string(),
+            start: start.to_string(),
+            seconds_until: seconds.parse().map_err(|_| invalid())?,
+        })),
+        _ => Err(invalid()),
+    }
+}
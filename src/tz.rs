@@ -0,0 +1,574 @@
+//! IANA time zones read from the system zoneinfo database, usable as a chrono `TimeZone`.
+
+use std::{env, fmt, fs, io};
+
+use chrono::{
+    Datelike, FixedOffset, Local, LocalResult, NaiveDate, NaiveDateTime, Offset, TimeZone,
+};
+
+/// Where zoneinfo files live unless `$TZDIR` says otherwise.
+const DEFAULT_TZDIR: &str = "/usr/share/zoneinfo";
+
+/// Either the system's local time zone or a named IANA zone.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum Zone {
+    #[default]
+    Local,
+    /// Zone data is leaked so `Zone` is `Copy` like chrono's own zones. It's only loaded
+    /// along with the config, a few KB each time.
+    Named(&'static TzInfo),
+}
+
+impl Zone {
+    /// Load the IANA zone `name`, e.g. `Europe/Berlin`, from the zoneinfo database.
+    pub fn named(name: &str) -> io::Result<Self> {
+        if name.is_empty() || name.starts_with('/') || name.split('/').any(|part| part == "..") {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "not a time zone name",
+            ));
+        }
+
+        let dir = env::var("TZDIR").unwrap_or_else(|_| DEFAULT_TZDIR.to_string());
+        let data = fs::read(format!("{}/{}", dir, name))?;
+        let info = TzInfo::parse(&data)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed zoneinfo file"))?;
+
+        Ok(Zone::Named(Box::leak(Box::new(info))))
+    }
+
+    fn offset(&self, fixed: FixedOffset) -> ZoneOffset {
+        ZoneOffset { zone: *self, fixed }
+    }
+}
+
+/// The offset of a `Zone` at a particular instant.
+#[derive(Debug, Clone, Copy)]
+pub struct ZoneOffset {
+    zone: Zone,
+    fixed: FixedOffset,
+}
+
+impl Offset for ZoneOffset {
+    fn fix(&self) -> FixedOffset {
+        self.fixed
+    }
+}
+
+impl fmt::Display for ZoneOffset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fixed.fmt(f)
+    }
+}
+
+impl TimeZone for Zone {
+    type Offset = ZoneOffset;
+
+    fn from_offset(offset: &ZoneOffset) -> Self {
+        offset.zone
+    }
+
+    fn offset_from_local_date(&self, local: &NaiveDate) -> LocalResult<ZoneOffset> {
+        self.offset_from_local_datetime(&local.and_hms(0, 0, 0))
+    }
+
+    fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<ZoneOffset> {
+        match self {
+            Zone::Local => Local
+                .offset_from_local_datetime(local)
+                .map(|offset| self.offset(offset)),
+            Zone::Named(info) => info
+                .local_offset(local.timestamp())
+                .map(|secs| self.offset(FixedOffset::east(secs))),
+        }
+    }
+
+    fn offset_from_utc_date(&self, utc: &NaiveDate) -> ZoneOffset {
+        self.offset_from_utc_datetime(&utc.and_hms(0, 0, 0))
+    }
+
+    fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> ZoneOffset {
+        match self {
+            Zone::Local => self.offset(Local.offset_from_utc_datetime(utc)),
+            Zone::Named(info) => self.offset(FixedOffset::east(info.utc_offset(utc.timestamp()))),
+        }
+    }
+}
+
+/// The contents of a TZif file: historic transitions plus a rule for everything after them.
+#[derive(Debug)]
+pub struct TzInfo {
+    /// UTC instants at which the offset changes, ascending.
+    transitions: Vec<i64>,
+    /// Offset from UTC in seconds that takes effect at the matching transition.
+    offsets: Vec<i32>,
+    /// Offset before the first transition.
+    initial: i32,
+    /// Rule from the TZif footer for instants after the last transition.
+    rule: Option<Rule>,
+}
+
+impl TzInfo {
+    /// Parse a TZif file, preferring the 64-bit data of version 2+ files.
+    fn parse(data: &[u8]) -> Option<Self> {
+        let header = Header::parse(data)?;
+        if header.version < b'2' {
+            return Self::parse_block(data, &header, 4, None);
+        }
+
+        // skip the 32-bit block and read the 64-bit one that follows it, plus the footer.
+        let data = data.get(header.block_len(4)..)?;
+        let header = Header::parse(data)?;
+        let footer = data.get(header.block_len(8)..)?;
+        let rule = std::str::from_utf8(footer)
+            .ok()
+            .and_then(|footer| footer.trim().lines().next())
+            .and_then(Rule::parse);
+
+        Self::parse_block(data, &header, 8, rule)
+    }
+
+    fn parse_block(
+        data: &[u8],
+        header: &Header,
+        time_size: usize,
+        rule: Option<Rule>,
+    ) -> Option<Self> {
+        let mut data = data.get(HEADER_LEN..)?;
+        let mut take = |len: usize| -> Option<&[u8]> {
+            let (head, tail) = (data.get(..len)?, data.get(len..)?);
+            data = tail;
+            Some(head)
+        };
+
+        let times = take(header.timecnt * time_size)?;
+        let indices = take(header.timecnt)?;
+        let types = take(header.typecnt * 6)?;
+
+        let type_offset = |idx: usize| -> Option<i32> {
+            let ttinfo = types.get(idx * 6..idx * 6 + 4)?;
+            Some(i32::from_be_bytes([
+                ttinfo[0], ttinfo[1], ttinfo[2], ttinfo[3],
+            ]))
+        };
+
+        let transitions = times
+            .chunks(time_size)
+            .map(|chunk| {
+                let mut bytes = [0; 8];
+                bytes[8 - time_size..].copy_from_slice(chunk);
+                // sign-extend 32-bit times.
+                if time_size == 4 && chunk[0] & 0x80 != 0 {
+                    bytes[..4].copy_from_slice(&[0xff; 4]);
+                }
+                i64::from_be_bytes(bytes)
+            })
+            .collect();
+        let offsets = indices
+            .iter()
+            .map(|&idx| type_offset(idx as usize))
+            .collect::<Option<_>>()?;
+
+        Some(TzInfo {
+            transitions,
+            offsets,
+            initial: type_offset(0)?,
+            rule,
+        })
+    }
+
+    /// Offset from UTC in seconds at the UTC instant `utc`.
+    fn utc_offset(&self, utc: i64) -> i32 {
+        let after_transitions = self.transitions.last().is_none_or(|&last| utc >= last);
+        if let Some(rule) = self.rule.as_ref().filter(|_| after_transitions) {
+            // a rule that can't be worked out for this year is as good as none.
+            if let Some(offset) = rule.utc_offset(utc) {
+                return offset;
+            }
+        }
+
+        match self.transitions.partition_point(|&t| t <= utc) {
+            0 => self.initial,
+            idx => self.offsets[idx - 1],
+        }
+    }
+
+    /// The offsets under which `local` seconds (as if UTC) is a valid local time, if any.
+    fn local_offset(&self, local: i64) -> LocalResult<i32> {
+        // offsets only change a few times a year, so the ones in effect a day either side
+        // are the only candidates.
+        let mut candidates = vec![
+            self.utc_offset(local - 86400),
+            self.utc_offset(local + 86400),
+        ];
+        candidates.sort_unstable();
+        candidates.dedup();
+
+        // the larger offset maps to the earlier instant.
+        let valid: Vec<i32> = candidates
+            .into_iter()
+            .rev()
+            .filter(|&offset| self.utc_offset(local - offset as i64) == offset)
+            .collect();
+
+        match valid[..] {
+            [] => LocalResult::None,
+            [offset] => LocalResult::Single(offset),
+            [earliest, latest, ..] => LocalResult::Ambiguous(earliest, latest),
+        }
+    }
+}
+
+const HEADER_LEN: usize = 44;
+
+/// The counts in a TZif header, see RFC 8536.
+struct Header {
+    version: u8,
+    isutcnt: usize,
+    isstdcnt: usize,
+    leapcnt: usize,
+    timecnt: usize,
+    typecnt: usize,
+    charcnt: usize,
+}
+
+impl Header {
+    fn parse(data: &[u8]) -> Option<Self> {
+        if data.get(..4)? != b"TZif" {
+            return None;
+        }
+
+        let count = |idx: usize| -> Option<usize> {
+            let bytes = data.get(20 + idx * 4..24 + idx * 4)?;
+            Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+        };
+
+        Some(Header {
+            version: *data.get(4)?,
+            isutcnt: count(0)?,
+            isstdcnt: count(1)?,
+            leapcnt: count(2)?,
+            timecnt: count(3)?,
+            typecnt: count(4)?,
+            charcnt: count(5)?,
+        })
+    }
+
+    /// Length of the header and the data block following it.
+    fn block_len(&self, time_size: usize) -> usize {
+        HEADER_LEN
+            + self.timecnt * (time_size + 1)
+            + self.typecnt * 6
+            + self.charcnt
+            + self.leapcnt * (time_size + 4)
+            + self.isstdcnt
+            + self.isutcnt
+    }
+}
+
+/// A POSIX TZ rule like `CET-1CEST,M3.5.0,M10.5.0/3`.
+#[derive(Debug)]
+struct Rule {
+    /// Standard offset from UTC in seconds, east positive.
+    std_offset: i32,
+    /// Daylight saving time, if the zone observes it.
+    dst: Option<DstRule>,
+}
+
+#[derive(Debug)]
+struct DstRule {
+    offset: i32,
+    start: (RuleDate, i32),
+    end: (RuleDate, i32),
+}
+
+#[derive(Debug)]
+enum RuleDate {
+    /// `Jn`, day of the year from 1 to 365, never counting February 29th.
+    Julian(u32),
+    /// `n`, zero-based day of the year counting February 29th.
+    Ordinal(u32),
+    /// `Mm.w.d`, day `d` (0 is Sunday) of week `w` (5 is the last) of month `m`.
+    Month { month: u32, week: u32, weekday: u32 },
+}
+
+impl Rule {
+    fn parse(s: &str) -> Option<Self> {
+        let mut s = s;
+
+        skip_name(&mut s)?;
+        let std_offset = -parse_offset(&mut s)?;
+        if s.is_empty() {
+            return Some(Rule {
+                std_offset,
+                dst: None,
+            });
+        }
+
+        skip_name(&mut s)?;
+        let offset = match s.starts_with(',') {
+            true => std_offset + 3600,
+            false => -parse_offset(&mut s)?,
+        };
+
+        s = s.strip_prefix(',')?;
+        let start = parse_transition(&mut s)?;
+        s = s.strip_prefix(',')?;
+        let end = parse_transition(&mut s)?;
+
+        Some(Rule {
+            std_offset,
+            dst: Some(DstRule { offset, start, end }),
+        })
+    }
+
+    /// Offset from UTC in seconds at the UTC instant `utc`, `None` if the year's transitions
+    /// fall on days it doesn't have.
+    fn utc_offset(&self, utc: i64) -> Option<i32> {
+        let dst = match &self.dst {
+            Some(dst) => dst,
+            None => return Some(self.std_offset),
+        };
+
+        let year = NaiveDateTime::from_timestamp(utc + self.std_offset as i64, 0).year();
+        // transitions are given in the local time in effect just before them.
+        let start = transition_utc(year, &dst.start)? - self.std_offset as i64;
+        let end = transition_utc(year, &dst.end)? - dst.offset as i64;
+
+        let in_dst = if start < end {
+            start <= utc && utc < end
+        } else {
+            // southern hemisphere, daylight saving time spans the new year.
+            !(end <= utc && utc < start)
+        };
+
+        Some(match in_dst {
+            true => dst.offset,
+            false => self.std_offset,
+        })
+    }
+}
+
+/// Seconds since the epoch, as if local time were UTC, of a rule transition in `year`. `None`
+/// for a day of the year that `year` doesn't have, like `J366` or `365` outside leap years.
+fn transition_utc(year: i32, (date, time): &(RuleDate, i32)) -> Option<i64> {
+    let date = match *date {
+        RuleDate::Julian(day) => {
+            let leap = NaiveDate::from_ymd_opt(year, 2, 29).is_some();
+            let ordinal = if leap && day >= 60 { day + 1 } else { day };
+            NaiveDate::from_yo_opt(year, ordinal)?
+        }
+        RuleDate::Ordinal(day) => NaiveDate::from_yo_opt(year, day + 1)?,
+        RuleDate::Month {
+            month,
+            week,
+            weekday,
+        } => {
+            let first = NaiveDate::from_ymd(year, month, 1);
+            let first_weekday = first.weekday().num_days_from_sunday();
+            let mut day = 1 + (weekday + 7 - first_weekday) % 7 + (week - 1) * 7;
+            while NaiveDate::from_ymd_opt(year, month, day).is_none() {
+                day -= 7;
+            }
+            NaiveDate::from_ymd(year, month, day)
+        }
+    };
+
+    Some(date.and_hms(0, 0, 0).timestamp() + *time as i64)
+}
+
+/// Skip a zone abbreviation, either alphabetic or quoted in `<>`.
+fn skip_name(s: &mut &str) -> Option<()> {
+    let len = match s.strip_prefix('<') {
+        Some(quoted) => quoted.find('>')? + 2,
+        None => s
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(s.len()),
+    };
+    if len < 3 {
+        return None;
+    }
+    *s = &s[len..];
+    Some(())
+}
+
+/// Parse `[+-]hh[:mm[:ss]]` into seconds, as written (POSIX offsets are west positive).
+fn parse_offset(s: &mut &str) -> Option<i32> {
+    let sign = match s.chars().next()? {
+        '-' => -1,
+        '+' => 1,
+        _ => 0,
+    };
+    if sign != 0 {
+        *s = &s[1..];
+    }
+
+    let len = s
+        .find(|c: char| !(c.is_ascii_digit() || c == ':'))
+        .unwrap_or(s.len());
+    let mut secs = 0;
+    for (idx, part) in s[..len].split(':').enumerate() {
+        if idx > 2 {
+            return None;
+        }
+        secs += part.parse::<i32>().ok()? * [3600, 60, 1][idx];
+    }
+    *s = &s[len..];
+
+    Some(if sign < 0 { -secs } else { secs })
+}
+
+/// Parse a `date[/time]` transition, the time defaulting to 02:00.
+fn parse_transition(s: &mut &str) -> Option<(RuleDate, i32)> {
+    let len = s.find([',', '/']).unwrap_or(s.len());
+    let (date, rest) = s.split_at(len);
+
+    let date = if let Some(julian) = date.strip_prefix('J') {
+        RuleDate::Julian(julian.parse().ok()?)
+    } else if let Some(month) = date.strip_prefix('M') {
+        let mut parts = month.split('.').map(|part| part.parse::<u32>().ok());
+        let (month, week, weekday) = (parts.next()??, parts.next()??, parts.next()??);
+        if !(1..=12).contains(&month) || !(1..=5).contains(&week) || weekday > 6 {
+            return None;
+        }
+        RuleDate::Month {
+            month,
+            week,
+            weekday,
+        }
+    } else {
+        RuleDate::Ordinal(date.parse().ok()?)
+    };
+
+    *s = rest;
+    let time = match s.strip_prefix('/') {
+        Some(time) => {
+            *s = time;
+            parse_offset(s)?
+        }
+        None => 2 * 3600,
+    };
+
+    Some((date, time))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A zone that follows `rule` throughout, like a TZif file with only a footer.
+    fn zone(rule: &str) -> Zone {
+        let rule = Rule::parse(rule).unwrap();
+        Zone::Named(Box::leak(Box::new(TzInfo {
+            transitions: Vec::new(),
+            offsets: Vec::new(),
+            initial: rule.std_offset,
+            rule: Some(rule),
+        })))
+    }
+
+    fn utc(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    fn offset_at(zone: &Zone, text: &str) -> i32 {
+        zone.offset_from_utc_datetime(&utc(text))
+            .fix()
+            .local_minus_utc()
+    }
+
+    #[test]
+    fn offsets_either_side_of_the_transitions() {
+        let berlin = zone("CET-1CEST,M3.5.0,M10.5.0/3");
+
+        // in 2024 summer time ran from March 31st to October 27th, 01:00 UTC both times.
+        assert_eq!(offset_at(&berlin, "2024-03-31 00:59:59"), 3600);
+        assert_eq!(offset_at(&berlin, "2024-03-31 01:00:00"), 7200);
+        assert_eq!(offset_at(&berlin, "2024-10-27 00:59:59"), 7200);
+        assert_eq!(offset_at(&berlin, "2024-10-27 01:00:00"), 3600);
+    }
+
+    #[test]
+    fn local_times_in_the_gap_and_the_overlap() {
+        let berlin = zone("CET-1CEST,M3.5.0,M10.5.0/3");
+        let local = |text: &str| berlin.offset_from_local_datetime(&utc(text));
+        let secs = |offset: ZoneOffset| offset.fix().local_minus_utc();
+
+        // clocks jump from 02:00 to 03:00 in spring...
+        assert!(matches!(local("2024-03-31 02:30:00"), LocalResult::None));
+        assert_eq!(
+            local("2024-03-31 01:59:59").map(secs),
+            LocalResult::Single(3600)
+        );
+        assert_eq!(
+            local("2024-03-31 03:00:00").map(secs),
+            LocalResult::Single(7200)
+        );
+
+        // ...and go from 03:00 back to 02:00 in autumn.
+        assert_eq!(
+            local("2024-10-27 02:30:00").map(secs),
+            LocalResult::Ambiguous(7200, 3600)
+        );
+        assert_eq!(
+            local("2024-10-27 01:59:59").map(secs),
+            LocalResult::Single(7200)
+        );
+        assert_eq!(
+            local("2024-10-27 03:00:00").map(secs),
+            LocalResult::Single(3600)
+        );
+    }
+
+    #[test]
+    fn eight_oclock_on_the_transition_days() {
+        let berlin = zone("CET-1CEST,M3.5.0,M10.5.0/3");
+        let at_eight = |date: &str| {
+            let local = utc(&format!("{} 08:00:00", date));
+            berlin
+                .from_local_datetime(&local)
+                .single()
+                .unwrap()
+                .naive_utc()
+        };
+
+        assert_eq!(at_eight("2024-03-30"), utc("2024-03-30 07:00:00"));
+        assert_eq!(at_eight("2024-03-31"), utc("2024-03-31 06:00:00"));
+        assert_eq!(at_eight("2024-10-26"), utc("2024-10-26 06:00:00"));
+        assert_eq!(at_eight("2024-10-27"), utc("2024-10-27 07:00:00"));
+    }
+
+    #[test]
+    fn southern_hemisphere_rule() {
+        // Sydney's summer time spans the new year.
+        let sydney = zone("AEST-10AEDT,M10.1.0,M4.1.0/3");
+        assert_eq!(offset_at(&sydney, "2024-01-15 00:00:00"), 11 * 3600);
+        assert_eq!(offset_at(&sydney, "2024-07-15 00:00:00"), 10 * 3600);
+    }
+
+    #[test]
+    fn days_a_year_does_not_have() {
+        // 2023 has no 366th day, nor a day 365 counting from zero.
+        let rule = Rule::parse("EST5EDT,J366,365").unwrap();
+        let instant = utc("2023-06-01 00:00:00").timestamp();
+        assert_eq!(rule.utc_offset(instant), None);
+
+        // so the zone falls back to what it knows without the rule.
+        let zone = zone("EST5EDT,J366,365");
+        assert_eq!(offset_at(&zone, "2023-06-01 00:00:00"), -5 * 3600);
+    }
+
+    #[test]
+    fn truncated_file_is_rejected() {
+        let mut data = b"TZif2".to_vec();
+        data.resize(HEADER_LEN, 0);
+        // claim a hundred transitions without any data for them.
+        data[32..36].copy_from_slice(&100u32.to_be_bytes());
+        assert!(TzInfo::parse(&data).is_none());
+
+        // a complete, empty first block, but nothing after it.
+        let mut data = b"TZif2".to_vec();
+        data.resize(HEADER_LEN, 0);
+        assert!(TzInfo::parse(&data).is_none());
+    }
+}